@@ -7,7 +7,11 @@ use intear_oracle::fees::PrepaidFee;
 use intear_oracle::producer::{ext_oracle_responder, ProducerContract, Response};
 use near_sdk::Gas;
 use near_sdk::{
-    env, near, store::LookupMap, AccountId, NearToken, PanicOnDefault, Promise, PromiseError,
+    env,
+    json_types::{Base58CryptoHash, Base64VecU8},
+    near,
+    store::LookupMap,
+    AccountId, NearToken, PanicOnDefault, Promise, PromiseError,
 };
 use types::{TxWithProof, H256};
 
@@ -92,7 +96,15 @@ impl Contract {
 
 #[near]
 impl ProducerContract for Contract {
-    fn on_request(&mut self, request_id: RequestId, request_data: String, prepaid_fee: PrepaidFee) {
+    fn on_request(
+        &mut self,
+        request_id: RequestId,
+        request_data: String,
+        prepaid_fee: PrepaidFee,
+        _end_user: Option<String>,
+        _request_bytes: Option<Base64VecU8>,
+        _request_blob: Option<Base58CryptoHash>,
+    ) {
         if env::predecessor_account_id() != self.oracle_contract {
             env::panic_str("Only oracle contract can call this method");
         }
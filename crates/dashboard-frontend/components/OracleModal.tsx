@@ -90,7 +90,7 @@ export const OracleModal = ({ oracle, isOpen, onClose }: OracleModalProps) => {
                             <Box>
                                 <Heading size="sm" mb={2}>2. Register your account as a consumer in order to have a balance</Heading>
                                 <CopyableCode
-                                    code={`near contract call-function as-transaction dev-unaudited-v1.oracle.intear.near register_consumer json-args '{"account_id": "<YOUR_ACCOUNT_ID>"}' prepaid-gas '100.0 Tgas' attached-deposit '0 NEAR' sign-as <YOUR_ACCOUNT_ID> network-config mainnet sign-with-keychain send`}
+                                    code={`near contract call-function as-transaction dev-unaudited-v1.oracle.intear.near register_consumer json-args '{"account_id": "<YOUR_ACCOUNT_ID>"}' prepaid-gas '100.0 Tgas' attached-deposit '0.01 NEAR' sign-as <YOUR_ACCOUNT_ID> network-config mainnet sign-with-keychain send`}
                                     language="bash"
                                 />
                             </Box>
@@ -26,7 +26,7 @@ impl Contract {
         ext_oracle_consumer::ext(self.oracle_contract.clone())
             .with_static_gas(Gas::from_tgas(10))
             .with_attached_deposit(NearToken::from_millinear(10)) // attach 0.01N fee
-            .request(self.producer_id.clone(), prompt)
+            .request(self.producer_id.clone(), prompt, None)
             .then(Self::ext(env::current_account_id()).on_response())
     }
 
@@ -21,6 +21,7 @@ const ORACLE_CONTRACT: &str = "dev-unaudited-v1.oracle.intear.near";
 struct OracleRequestEvent {
     producer_id: AccountId,
     consumer_id: AccountId,
+    end_user: Option<String>,
     #[serde(with = "dec_format")]
     request_id: u128,
     request_data: String,
@@ -58,7 +59,7 @@ impl GptOracle {
             .header("Authorization", format!("Bearer {}", self.openai_api_key))
             .json(&serde_json::json!({
                 "model": self.model,
-                "user": event.consumer_id,
+                "user": event.end_user.unwrap_or_else(|| event.consumer_id.to_string()),
                 "messages": [
                     {
                         "role": "system",
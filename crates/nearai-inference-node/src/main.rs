@@ -20,6 +20,7 @@ const ORACLE_CONTRACT: &str = "dev-unaudited-v1.oracle.intear.near";
 struct OracleRequestEvent {
     producer_id: AccountId,
     consumer_id: AccountId,
+    end_user: Option<String>,
     #[serde(with = "dec_format")]
     request_id: u128,
     request_data: String,
@@ -64,7 +65,7 @@ impl GptOracle {
             .header("Authorization", format!("Bearer {}", self.auth))
             .json(&serde_json::json!({
                 "model": msg.model,
-                "user": event.consumer_id,
+                "user": event.end_user.unwrap_or_else(|| event.consumer_id.to_string()),
                 "messages": msg.messages,
             }))
             .send()
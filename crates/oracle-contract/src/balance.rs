@@ -4,19 +4,70 @@ use near_sdk::{
 };
 use near_sdk_contract_tools::ft::{ext_nep141, Nep141Receiver};
 
-use crate::producer::ProducerId;
+use crate::{
+    consumer::{ConsumerId, OracleEvent},
+    producer::ProducerId,
+};
 #[cfg(feature = "contract")]
 use crate::{Oracle, OracleExt};
 
 pub type FtId = AccountId;
 
+/// A part of the consumer's balance.
+#[near(serializers=[json])]
+#[derive(Clone, Debug, PartialEq)]
+pub enum BalanceBucket {
+    /// `near_balance` or `ft_balances`, used to pay for requests
+    /// to all producers.
+    ForAllProducers,
+    /// `near_balance_producer` or `ft_balances_producer`, used to
+    /// pay for requests to a specific producer.
+    ForSpecificProducer { producer_id: ProducerId },
+}
+
+#[near(serializers=["json"])]
+pub struct BalanceTransferEventV1 {
+    pub from_account_id: ConsumerId,
+    pub from_bucket: BalanceBucket,
+    pub to_account_id: ConsumerId,
+    pub to_bucket: BalanceBucket,
+    /// `None` for NEAR.
+    pub token: Option<FtId>,
+    pub amount: U128,
+}
+
 #[cfg(feature = "contract")]
 #[near]
 impl Oracle {
+    /// Deposits the attached NEAR to the consumer's balance. If
+    /// `allocations` are set, the deposit is split between balances
+    /// for specific producers, and the remainder goes to the general
+    /// balance.
     #[payable]
-    pub fn deposit_near(&mut self, account_id: Option<AccountId>, producer_id: Option<ProducerId>) {
+    pub fn deposit_near(
+        &mut self,
+        account_id: Option<AccountId>,
+        producer_id: Option<ProducerId>,
+        allocations: Option<Vec<(ProducerId, NearToken)>>,
+    ) {
         let amount = env::attached_deposit();
         let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        if let Some(allocations) = allocations {
+            near_sdk::require!(
+                producer_id.is_none(),
+                "Can't use producer_id together with allocations"
+            );
+            self.deposit_with_allocations(
+                &account_id,
+                None,
+                U128(amount.as_yoctonear()),
+                allocations
+                    .into_iter()
+                    .map(|(producer_id, amount)| (producer_id, U128(amount.as_yoctonear())))
+                    .collect(),
+            );
+            return;
+        }
         let consumer = self
             .consumers
             .get_mut(&account_id)
@@ -83,6 +134,7 @@ impl Oracle {
 
     #[payable]
     pub fn withdraw_near(&mut self, amount: NearToken, producer_id: Option<ProducerId>) {
+        near_sdk::assert_one_yocto();
         let account_id = env::predecessor_account_id();
         let consumer = self
             .consumers
@@ -116,6 +168,7 @@ impl Oracle {
 
     #[payable]
     pub fn withdraw_ft(&mut self, amount: U128, producer_id: Option<ProducerId>, ft_id: FtId) {
+        near_sdk::assert_one_yocto();
         let account_id = env::predecessor_account_id();
         let consumer = self
             .consumers
@@ -167,12 +220,240 @@ impl Oracle {
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .ft_transfer(account_id, amount, None);
     }
+
+    /// Moves NEAR (if `token` is `None`) or fungible tokens between
+    /// the caller's balance buckets, or to a bucket of another
+    /// consumer if `to_account` is set.
+    pub fn transfer_balance(
+        &mut self,
+        from_bucket: BalanceBucket,
+        to_bucket: BalanceBucket,
+        to_account: Option<ConsumerId>,
+        token: Option<FtId>,
+        amount: U128,
+    ) {
+        let from_account_id = env::predecessor_account_id();
+        let to_account_id = to_account.unwrap_or_else(|| from_account_id.clone());
+        near_sdk::require!(amount.0 > 0, "Amount must be positive");
+        near_sdk::require!(
+            from_account_id != to_account_id || from_bucket != to_bucket,
+            "Can't transfer to the same bucket"
+        );
+        near_sdk::require!(
+            self.consumers.contains_key(&to_account_id),
+            "Receiver is not registered"
+        );
+        if let BalanceBucket::ForSpecificProducer { producer_id } = &to_bucket {
+            near_sdk::require!(
+                self.producers.contains_key(producer_id),
+                "Producer doesn't exist"
+            );
+        }
+
+        self.debit_balance(&from_account_id, &from_bucket, token.as_ref(), amount);
+        self.credit_balance(&to_account_id, &to_bucket, token.as_ref(), amount);
+
+        OracleEvent::BalanceTransfer(BalanceTransferEventV1 {
+            from_account_id,
+            from_bucket,
+            to_account_id,
+            to_bucket,
+            token,
+            amount,
+        })
+        .emit();
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Splits a deposit between balances for specific producers. All
+    /// allocations are validated before any balance is touched, and
+    /// the remainder goes to the general balance.
+    pub fn deposit_with_allocations(
+        &mut self,
+        account_id: &ConsumerId,
+        token: Option<&FtId>,
+        amount: U128,
+        allocations: Vec<(ProducerId, U128)>,
+    ) {
+        near_sdk::require!(
+            self.consumers.contains_key(account_id),
+            "Consumer is not registered"
+        );
+        let mut allocated: u128 = 0;
+        for (producer_id, allocation) in allocations.iter() {
+            near_sdk::require!(
+                self.producers.contains_key(producer_id),
+                "Producer doesn't exist"
+            );
+            allocated = allocated.checked_add(allocation.0).expect("Overflow");
+        }
+        let remainder = amount
+            .0
+            .checked_sub(allocated)
+            .expect("Allocations exceed the deposited amount");
+
+        let format_amount = |amount: u128| match token {
+            Some(ft_id) => format!("{amount} {ft_id}"),
+            None => NearToken::from_yoctonear(amount).to_string(),
+        };
+        for (producer_id, allocation) in allocations {
+            log!(
+                "Deposited {amount} to {account_id} for {producer_id}",
+                amount = format_amount(allocation.0)
+            );
+            self.credit_balance(
+                account_id,
+                &BalanceBucket::ForSpecificProducer { producer_id },
+                token,
+                allocation,
+            );
+        }
+        if remainder > 0 {
+            log!(
+                "Deposited {amount} to {account_id}",
+                amount = format_amount(remainder)
+            );
+            self.credit_balance(
+                account_id,
+                &BalanceBucket::ForAllProducers,
+                token,
+                U128(remainder),
+            );
+        }
+    }
+
+    /// Adds NEAR (if `token` is `None`) or fungible tokens to a
+    /// balance bucket of the consumer.
+    pub fn credit_balance(
+        &mut self,
+        account_id: &ConsumerId,
+        bucket: &BalanceBucket,
+        token: Option<&FtId>,
+        amount: U128,
+    ) {
+        let consumer = self
+            .consumers
+            .get_mut(account_id)
+            .expect("Consumer is not registered");
+        match (bucket, token) {
+            (BalanceBucket::ForAllProducers, None) => {
+                consumer.near_balance = consumer
+                    .near_balance
+                    .checked_add(NearToken::from_yoctonear(amount.0))
+                    .expect("Overflow");
+            }
+            (BalanceBucket::ForSpecificProducer { producer_id }, None) => {
+                let amount = NearToken::from_yoctonear(amount.0);
+                if let Some(near_balance) = consumer.near_balance_producer.get_mut(producer_id) {
+                    *near_balance = near_balance.checked_add(amount).expect("Overflow");
+                } else {
+                    consumer
+                        .near_balance_producer
+                        .insert(producer_id.clone(), amount);
+                }
+            }
+            (BalanceBucket::ForAllProducers, Some(ft_id)) => {
+                if let Some(ft_balance) = consumer.ft_balances.get_mut(ft_id) {
+                    *ft_balance = ft_balance.0.checked_add(amount.0).expect("Overflow").into();
+                } else {
+                    consumer.ft_balances.insert(ft_id.clone(), amount);
+                }
+            }
+            (BalanceBucket::ForSpecificProducer { producer_id }, Some(ft_id)) => {
+                if let Some(ft_balance) = consumer
+                    .ft_balances_producer
+                    .get_mut(&(producer_id.clone(), ft_id.clone()))
+                {
+                    *ft_balance = ft_balance.0.checked_add(amount.0).expect("Overflow").into();
+                } else {
+                    consumer
+                        .ft_balances_producer
+                        .insert((producer_id.clone(), ft_id.clone()), amount);
+                }
+            }
+        }
+    }
+
+    /// Removes NEAR (if `token` is `None`) or fungible tokens from a
+    /// balance bucket of the consumer. Panics if there's not enough
+    /// balance.
+    pub fn debit_balance(
+        &mut self,
+        account_id: &ConsumerId,
+        bucket: &BalanceBucket,
+        token: Option<&FtId>,
+        amount: U128,
+    ) {
+        let consumer = self
+            .consumers
+            .get_mut(account_id)
+            .expect("Consumer is not registered");
+        match (bucket, token) {
+            (BalanceBucket::ForAllProducers, None) => {
+                let amount = NearToken::from_yoctonear(amount.0);
+                near_sdk::require!(consumer.near_balance >= amount, "Not enough balance");
+                consumer.near_balance = consumer
+                    .near_balance
+                    .checked_sub(amount)
+                    .expect("Underflow");
+            }
+            (BalanceBucket::ForSpecificProducer { producer_id }, None) => {
+                let amount = NearToken::from_yoctonear(amount.0);
+                let near_balance = consumer
+                    .near_balance_producer
+                    .get_mut(producer_id)
+                    .expect("No balance for producer");
+                near_sdk::require!(*near_balance >= amount, "Not enough balance for producer");
+                *near_balance = near_balance.checked_sub(amount).expect("Underflow");
+                if near_balance.is_zero() {
+                    consumer.near_balance_producer.remove(producer_id);
+                }
+            }
+            (BalanceBucket::ForAllProducers, Some(ft_id)) => {
+                let ft_balance = consumer.ft_balances.get_mut(ft_id).expect("No balance");
+                near_sdk::require!(*ft_balance >= amount, "Not enough balance");
+                *ft_balance = ft_balance
+                    .0
+                    .checked_sub(amount.0)
+                    .expect("Underflow")
+                    .into();
+                if *ft_balance == 0.into() {
+                    consumer.ft_balances.remove(ft_id);
+                }
+            }
+            (BalanceBucket::ForSpecificProducer { producer_id }, Some(ft_id)) => {
+                let key = (producer_id.clone(), ft_id.clone());
+                let ft_balance = consumer
+                    .ft_balances_producer
+                    .get_mut(&key)
+                    .expect("No balance for producer");
+                near_sdk::require!(*ft_balance >= amount, "Not enough balance for producer");
+                *ft_balance = ft_balance
+                    .0
+                    .checked_sub(amount.0)
+                    .expect("Underflow")
+                    .into();
+                if *ft_balance == 0.into() {
+                    consumer.ft_balances_producer.remove(&key);
+                }
+            }
+        }
+    }
 }
 
 #[near(serializers=[json])]
 struct FtDepositArgs {
     pub account_id: Option<AccountId>,
     pub producer_id: Option<ProducerId>,
+    /// If true, the tokens are deposited to the sponsorship pool of
+    /// `producer_id` instead of a consumer's balance.
+    #[serde(default)]
+    pub sponsorship: bool,
+    /// Amounts to deposit to balances for specific producers. The
+    /// remainder goes to the general balance.
+    pub allocations: Option<Vec<(ProducerId, U128)>>,
 }
 
 #[cfg(feature = "contract")]
@@ -185,8 +466,31 @@ impl Nep141Receiver for Oracle {
         msg: String,
     ) -> PromiseOrValue<U128> {
         let args = serde_json::from_str::<FtDepositArgs>(&msg).expect("Invalid msg");
-        let account_id = args.account_id.unwrap_or(sender_id);
         let ft_id = env::predecessor_account_id();
+        if args.sponsorship {
+            let producer_id = args
+                .producer_id
+                .expect("producer_id is required for sponsorship deposits");
+            near_sdk::require!(
+                self.producers.contains_key(&producer_id),
+                "Producer doesn't exist"
+            );
+            self.credit_sponsorship_ft(&producer_id, &ft_id, amount);
+            log!(
+                "Deposited {amount} {ft_id} to sponsorship pool of {producer_id}",
+                amount = amount.0
+            );
+            return PromiseOrValue::Value(U128(0));
+        }
+        let account_id = args.account_id.unwrap_or(sender_id);
+        if let Some(allocations) = args.allocations {
+            near_sdk::require!(
+                args.producer_id.is_none(),
+                "Can't use producer_id together with allocations"
+            );
+            self.deposit_with_allocations(&account_id, Some(&ft_id), amount, allocations);
+            return PromiseOrValue::Value(U128(0));
+        }
         let consumer = self
             .consumers
             .get_mut(&account_id)
@@ -0,0 +1,181 @@
+use near_sdk::{
+    env,
+    json_types::{Base58CryptoHash, Base64VecU8},
+    log, near, AccountId, BlockHeight, CryptoHash, NearToken, Promise,
+};
+
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// Number of blocks (about a day) that a response blob is kept for
+/// the consumer. After that, anyone can remove it with `remove_blob`
+/// and the storage deposit goes back to the producer.
+pub const RESPONSE_BLOB_RETENTION_BLOCKS: BlockHeight = 100_000;
+
+/// Data that is too large to be passed inline in `request_data` or
+/// `response_data`. Blobs are addressed by the account that uploaded
+/// them and the sha256 of their contents, so the same data uploaded
+/// by another account doesn't get in the way. Request blobs are
+/// removed once the request that references them completes, response
+/// blobs are kept until the consumer that received them removes them
+/// with `remove_blob`, or until they expire.
+#[near(serializers=[borsh])]
+pub struct Blob {
+    pub data: Vec<u8>,
+    pub storage_deposit: NearToken,
+    /// Number of requests and responses in flight that reference
+    /// this blob.
+    pub references: u32,
+    /// Whether the blob was referenced at least once. Blobs that were
+    /// never used can be removed by their owner.
+    pub used: bool,
+    /// Consumers that received the blob as `response_blob` and
+    /// haven't removed it yet.
+    pub holders: Vec<AccountId>,
+    /// Block after which anyone can remove the blob, set when it's
+    /// handed over to a consumer as `response_blob`.
+    pub expires_at_block: Option<BlockHeight>,
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Stores `data` and returns its sha256 hash, which the caller can
+    /// pass as `request_blob` to `request` or `response_blob` in a
+    /// `Response`. The caller has to attach enough NEAR to cover the
+    /// storage, the rest is refunded.
+    #[payable]
+    pub fn upload_blob(&mut self, data: Base64VecU8) -> Base58CryptoHash {
+        let owner = env::predecessor_account_id();
+        let hash = env::sha256_array(&data.0);
+        let key = (owner.clone(), hash);
+        if self.blobs.contains_key(&key) {
+            log!(
+                "Blob {} already exists",
+                String::from(&Base58CryptoHash::from(hash))
+            );
+            if !env::attached_deposit().is_zero() {
+                Promise::new(owner).transfer(env::attached_deposit());
+            }
+            return hash.into();
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        self.blobs.insert(
+            key.clone(),
+            Blob {
+                data: data.0,
+                storage_deposit: NearToken::from_near(0),
+                references: 0,
+                used: false,
+                holders: Vec::new(),
+                expires_at_block: None,
+            },
+        );
+        self.blobs.flush();
+        let storage_deposit = env::storage_byte_cost()
+            .saturating_mul((env::storage_usage() - initial_storage_usage) as u128);
+        near_sdk::require!(
+            env::attached_deposit() >= storage_deposit,
+            format!("Attach at least {storage_deposit} to pay for the blob storage")
+        );
+        self.blobs.get_mut(&key).unwrap().storage_deposit = storage_deposit;
+
+        let refund = env::attached_deposit().saturating_sub(storage_deposit);
+        if !refund.is_zero() {
+            Promise::new(owner).transfer(refund);
+        }
+        hash.into()
+    }
+
+    /// Returns a blob uploaded by `owner`. Request blobs are owned by
+    /// the consumer, and response blobs by the producer.
+    pub fn get_blob(&self, owner: AccountId, hash: Base58CryptoHash) -> Option<Base64VecU8> {
+        self.blobs
+            .get(&(owner, CryptoHash::from(hash)))
+            .map(|blob| blob.data.clone().into())
+    }
+
+    /// Removes a blob that was uploaded by the caller but never
+    /// referenced, and refunds its storage deposit. A consumer that
+    /// received the blob as `response_blob` calls this once it has
+    /// fetched the data, and the storage deposit is refunded to the
+    /// producer when no one else holds it. Once a response blob
+    /// expires, anyone can remove it.
+    pub fn remove_blob(&mut self, owner: AccountId, hash: Base58CryptoHash) {
+        let key = (owner, CryptoHash::from(hash));
+        let account_id = env::predecessor_account_id();
+        let blob = self.blobs.get_mut(&key).expect("Blob doesn't exist");
+        if let Some(index) = blob.holders.iter().position(|holder| *holder == account_id) {
+            blob.holders.swap_remove(index);
+        } else if blob
+            .expires_at_block
+            .is_some_and(|expires_at_block| env::block_height() > expires_at_block)
+        {
+            blob.holders.clear();
+        } else {
+            near_sdk::require!(key.0 == account_id, "Only the owner can remove the blob");
+            near_sdk::require!(
+                !blob.used && blob.references == 0,
+                "Blob is used by a request"
+            );
+        }
+        if blob.references == 0 && blob.holders.is_empty() {
+            self.remove_blob_and_refund(&key);
+        }
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Marks a blob uploaded by `owner` as used by a request or a
+    /// response, so that it can't be removed until the request
+    /// completes.
+    pub fn reference_blob(&mut self, owner: &AccountId, hash: Base58CryptoHash) {
+        let blob = self
+            .blobs
+            .get_mut(&(owner.clone(), CryptoHash::from(hash)))
+            .expect("Blob doesn't exist");
+        blob.references += 1;
+        blob.used = true;
+    }
+
+    /// Drops a reference to a blob once the request completes, and
+    /// removes the blob if nothing else references or holds it.
+    pub fn release_blob(&mut self, owner: &AccountId, hash: Base58CryptoHash) {
+        let key = (owner.clone(), CryptoHash::from(hash));
+        let Some(blob) = self.blobs.get_mut(&key) else {
+            return;
+        };
+        blob.references = blob.references.saturating_sub(1);
+        if blob.references == 0 && blob.holders.is_empty() {
+            self.remove_blob_and_refund(&key);
+        }
+    }
+
+    /// Drops a reference to a response blob and keeps it for the
+    /// consumer for `RESPONSE_BLOB_RETENTION_BLOCKS`.
+    pub fn hand_over_blob(
+        &mut self,
+        owner: &AccountId,
+        hash: Base58CryptoHash,
+        consumer_id: &AccountId,
+    ) {
+        let Some(blob) = self.blobs.get_mut(&(owner.clone(), CryptoHash::from(hash))) else {
+            return;
+        };
+        blob.references = blob.references.saturating_sub(1);
+        if !blob.holders.contains(consumer_id) {
+            blob.holders.push(consumer_id.clone());
+        }
+        blob.expires_at_block = Some(env::block_height() + RESPONSE_BLOB_RETENTION_BLOCKS);
+    }
+
+    fn remove_blob_and_refund(&mut self, key: &(AccountId, CryptoHash)) {
+        if let Some(blob) = self.blobs.remove(key) {
+            if !blob.storage_deposit.is_zero() {
+                Promise::new(key.0.clone()).transfer(blob.storage_deposit);
+            }
+        }
+    }
+}
@@ -1,29 +1,145 @@
 use near_sdk::{
+    borsh::{self, BorshDeserialize},
     env, ext_contract,
-    json_types::{U128, U64},
-    near, serde_json,
-    store::LookupMap,
-    AccountId, CryptoHash, Gas, GasWeight, NearToken,
+    json_types::{Base58CryptoHash, Base64VecU8, U128, U64},
+    log, near, serde_json,
+    store::IterableMap,
+    AccountId, BlockHeight, CryptoHash, EpochHeight, Gas, GasWeight, NearToken, Promise,
+    PromiseIndex,
 };
+use near_sdk_contract_tools::ft::ext_nep141;
+use std::collections::BTreeMap;
 
 use crate::{
-    balance::FtId,
+    balance::{BalanceTransferEventV1, FtId},
+    fees::{Beneficiary, PrepaidFee},
+    gas::{extension_gas, extra_on_response_gas},
+    idempotency::{request_hash, RequestStatus},
     producer::{ext_producer, Producer, ProducerId},
+    referral::ReferralCommissionEventV1,
     StorageKey,
 };
 #[cfg(feature = "contract")]
 use crate::{Oracle, OracleExt};
 
 const RESUMPTION_TOKEN_REGISTER: u64 = 69;
+const MAX_END_USER_LENGTH: usize = 1024;
+const MAX_REQUEST_BYTES_LENGTH: usize = 4096;
 
 pub type ConsumerId = AccountId;
 pub type RequestId = U64;
 
 type ResumptionToken = CryptoHash;
 
+/// Creates a yielded `on_response` callback that is resumed when the
+/// producer responds or extends the request.
+pub(crate) fn yield_on_response(context: &RequestContext) -> (PromiseIndex, ResumptionToken) {
+    let promise_idx = env::promise_yield_create(
+        "on_response",
+        &serde_json::to_vec(&serde_json::json!({ "context": context })).unwrap(),
+        context.yield_gas(),
+        GasWeight::default(),
+        RESUMPTION_TOKEN_REGISTER,
+    );
+    let resumption_token = if let Some(data) = env::read_register(RESUMPTION_TOKEN_REGISTER) {
+        if let Ok(resumption_token) = ResumptionToken::try_from(data) {
+            resumption_token
+        } else {
+            env::panic_str("Wrong register length")
+        }
+    } else {
+        env::panic_str("Register is empty")
+    };
+    (promise_idx, resumption_token)
+}
+
 #[near(serializers=[borsh, json])]
 pub struct PendingRequest {
     pub resumption_token: CryptoHash,
+    /// Block at which the request was made.
+    pub created_at_block: BlockHeight,
+    /// Number of times the producer extended the request.
+    pub extensions: u32,
+    /// `max_extensions` of the producer when the request was made.
+    /// Gas for these extensions is reserved up front, so raising
+    /// `max_extensions` later doesn't apply to pending requests.
+    pub max_extensions: u32,
+    /// Latest progress reported by the producer.
+    pub progress: Option<String>,
+    /// Last block at which the producer can respond, set by the
+    /// consumer with `deadline_blocks`.
+    pub deadline_block: Option<BlockHeight>,
+}
+
+/// Method that `on_response` calls with `request_id` and
+/// `response: Option<Response>` once the request succeeds or times
+/// out, for consumers that can't wait on the promise returned by
+/// `request`. Can't be a method of the oracle contract.
+#[derive(Clone, Debug)]
+#[near(serializers=[json])]
+pub struct ResponseCallback {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    pub gas: Gas,
+}
+
+/// Method that `on_response` calls with `request_id`, `producer_id`
+/// and `response: Response` before the fee is paid to the producer.
+/// If it returns `false`, the response is rejected and the consumer
+/// gets a full refund. Can only be used with producers that set
+/// `accepts_validators`, and can't be a method of the oracle contract.
+#[derive(Clone, Debug)]
+#[near(serializers=[json])]
+pub struct ResponseValidator {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    pub gas: Gas,
+}
+
+/// Everything `on_response` needs to settle a request, passed through
+/// the yield.
+#[near(serializers=[json])]
+pub struct RequestContext {
+    pub producer_id: ProducerId,
+    pub request_id: RequestId,
+    pub consumer_id: ConsumerId,
+    pub fee: PrepaidFee,
+    pub referrer: Option<AccountId>,
+    pub created_at_block: BlockHeight,
+    pub request_blob: Option<Base58CryptoHash>,
+    pub callback: Option<ResponseCallback>,
+    pub idempotency_key: Option<String>,
+    pub on_response_gas: Gas,
+    pub validator: Option<ResponseValidator>,
+    /// Number of times the request can still be extended. Each one
+    /// has `EXTENSION_GAS` reserved in the yielded callback.
+    pub extensions_left: u32,
+    /// Beneficiaries of the producer when the request was made. The
+    /// fee is split between them, since `on_response_gas` was checked
+    /// against their number.
+    pub beneficiaries: Vec<Beneficiary>,
+}
+
+impl RequestContext {
+    /// Gas to settle the request and call the consumer's callback.
+    pub fn settlement_gas(&self) -> Gas {
+        self.on_response_gas.saturating_add(
+            self.callback
+                .as_ref()
+                .map_or(Gas::from_gas(0), |callback| callback.gas),
+        )
+    }
+
+    /// Gas of the yielded `on_response` callback, including the
+    /// validator call and the extensions that are left.
+    pub fn yield_gas(&self) -> Gas {
+        self.on_response_gas
+            .saturating_add(extra_on_response_gas(
+                self.callback.as_ref().map(|callback| callback.gas),
+                self.validator.as_ref().map(|validator| validator.gas),
+            ))
+            .saturating_add(extension_gas(self.extensions_left))
+    }
 }
 
 /// A consumer is an account that requests data from a producer.
@@ -37,19 +153,127 @@ pub struct Consumer {
     /// Fungible token balances of the consumer that will be used
     /// to pay for requests to specific producers. This will be
     /// used first before the general `near_balance`.
-    pub near_balance_producer: LookupMap<ProducerId, NearToken>,
+    pub near_balance_producer: IterableMap<ProducerId, NearToken>,
     /// Fungible token balances of the consumer that will be used
     /// to pay for requests to all producers.
-    pub ft_balances: LookupMap<FtId, U128>,
+    pub ft_balances: IterableMap<FtId, U128>,
     /// Fungible token balances of the consumer that will be used
     /// to pay for requests to specific producers. This will be
     /// used first before the general `ft_balances`.
-    pub ft_balances_producer: LookupMap<(ProducerId, FtId), U128>,
-    /// Number of requests sent to producers.
+    pub ft_balances_producer: IterableMap<(ProducerId, FtId), U128>,
+    /// Number of requests that producers responded to.
     pub requests_succeeded: u64,
     /// Number of requests that timed out (indexer didn't respond
     /// within 200 blocks. 200 is a NEAR protocol-level parameter.
     pub requests_timed_out: u64,
+    /// Number of requests made to each producer, used for volume
+    /// discounts.
+    pub producer_usage: IterableMap<ProducerId, ProducerUsage>,
+    /// Number of requests that haven't received a response or
+    /// timed out yet.
+    pub requests_pending: u64,
+    /// Storage cost paid in `register_consumer`, returned when the
+    /// consumer unregisters.
+    pub storage_deposit: NearToken,
+    /// Total NEAR paid to producers, after refunds. Sponsored
+    /// requests are not counted.
+    pub near_spent: NearToken,
+    /// Total fungible tokens paid to producers, after refunds.
+    pub ft_spent: IterableMap<FtId, U128>,
+    /// Number of responses rejected by the consumer's validators.
+    pub requests_rejected: u64,
+}
+
+/// Requests a consumer made to a producer in the current and the
+/// previous epoch.
+#[near(serializers=[borsh])]
+pub struct ProducerUsage {
+    pub epoch_height: EpochHeight,
+    pub requests_this_epoch: u64,
+    pub requests_previous_epoch: u64,
+    pub requests_succeeded: u64,
+    pub requests_timed_out: u64,
+    pub requests_rejected: u64,
+}
+
+impl Consumer {
+    /// New consumer with no balances.
+    pub fn new(account_id: ConsumerId) -> Self {
+        Consumer {
+            account_id: account_id.clone(),
+            near_balance: NearToken::from_near(0),
+            near_balance_producer: IterableMap::new(StorageKey::ConsumerNearBalanceProducer {
+                consumer: account_id.clone(),
+            }),
+            ft_balances: IterableMap::new(StorageKey::ConsumerFtBalances {
+                consumer: account_id.clone(),
+            }),
+            ft_balances_producer: IterableMap::new(StorageKey::ConsumerFtBalancesProducers {
+                consumer: account_id.clone(),
+            }),
+            requests_succeeded: 0,
+            requests_timed_out: 0,
+            producer_usage: IterableMap::new(StorageKey::ConsumerProducerUsage {
+                consumer: account_id.clone(),
+            }),
+            requests_pending: 0,
+            storage_deposit: NearToken::from_near(0),
+            near_spent: NearToken::from_near(0),
+            ft_spent: IterableMap::new(StorageKey::ConsumerFtSpent {
+                consumer: account_id.clone(),
+            }),
+            requests_rejected: 0,
+        }
+    }
+}
+
+impl ProducerUsage {
+    /// Rolling number of requests, counting the current and the
+    /// previous epoch.
+    pub fn rolling_requests(&self, epoch_height: EpochHeight) -> u64 {
+        if self.epoch_height == epoch_height {
+            self.requests_this_epoch + self.requests_previous_epoch
+        } else if self.epoch_height + 1 == epoch_height {
+            self.requests_this_epoch
+        } else {
+            0
+        }
+    }
+
+    pub fn record_request(&mut self, epoch_height: EpochHeight) {
+        if self.epoch_height != epoch_height {
+            self.requests_previous_epoch = if self.epoch_height + 1 == epoch_height {
+                self.requests_this_epoch
+            } else {
+                0
+            };
+            self.requests_this_epoch = 0;
+            self.epoch_height = epoch_height;
+        }
+        self.requests_this_epoch += 1;
+    }
+}
+
+/// Usage statistics of a consumer, returned by `get_consumer_details`.
+#[near(serializers=[json])]
+pub struct ConsumerDetails {
+    pub account_id: ConsumerId,
+    pub requests_succeeded: u64,
+    pub requests_timed_out: u64,
+    pub requests_rejected: u64,
+    pub requests_pending: u64,
+    pub near_spent: NearToken,
+    pub ft_spent: Vec<(FtId, U128)>,
+    pub producers: Vec<ConsumerProducerDetails>,
+}
+
+/// Usage statistics of a consumer for one producer.
+#[near(serializers=[json])]
+pub struct ConsumerProducerDetails {
+    pub producer_id: ProducerId,
+    pub requests_succeeded: u64,
+    pub requests_timed_out: u64,
+    pub requests_rejected: u64,
 }
 
 #[near(event_json(standard = "intear-oracle"))]
@@ -60,6 +284,16 @@ pub enum OracleEvent {
     ProducerCreated(Producer),
     #[event_version("1.0.0")]
     ProducerUpdated(Producer),
+    #[event_version("1.0.0")]
+    BalanceTransfer(BalanceTransferEventV1),
+    #[event_version("1.0.0")]
+    ProducerTransferred(ProducerTransferredEventV1),
+    #[event_version("1.0.0")]
+    ReferralCommission(ReferralCommissionEventV1),
+    #[event_version("1.0.0")]
+    RequestAcknowledged(RequestAcknowledgedEventV1),
+    #[event_version("1.0.0")]
+    Progress(ProgressEventV1),
 }
 
 #[near(serializers=["json"])]
@@ -68,53 +302,450 @@ pub struct RequestEventV1 {
     pub consumer_id: ConsumerId,
     pub request_id: RequestId,
     pub request_data: String,
+    /// Binary request data, for example a Borsh-encoded struct.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_bytes: Option<Base64VecU8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<AccountId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tip: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_blob: Option<Base58CryptoHash>,
+    /// Identifier of the end user the consumer makes the request for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_user: Option<String>,
+    /// Number of blocks after which responses are rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_blocks: Option<BlockHeight>,
+}
+
+impl RequestEventV1 {
+    /// Decodes `request_bytes` as Borsh. Returns `None` if there are
+    /// no bytes or they don't match `T`.
+    pub fn parse_borsh<T: BorshDeserialize>(&self) -> Option<T> {
+        borsh::from_slice(&self.request_bytes.as_ref()?.0).ok()
+    }
+}
+
+#[near(serializers=["json"])]
+pub struct RequestAcknowledgedEventV1 {
+    pub producer_id: ProducerId,
+    pub request_id: RequestId,
+    /// Number of times the request was extended, including this
+    /// acknowledgement.
+    pub extensions: u32,
+}
+
+#[near(serializers=["json"])]
+pub struct ProgressEventV1 {
+    pub producer_id: ProducerId,
+    pub request_id: RequestId,
+    pub progress_data: String,
+}
+
+#[near(serializers=["json"])]
+pub struct ProducerTransferredEventV1 {
+    pub old_producer_id: ProducerId,
+    pub new_producer_id: ProducerId,
 }
 
 #[cfg(feature = "contract")]
 #[near]
 impl Oracle {
+    /// Registers `account_id` as a consumer. The caller, which can be
+    /// another account, has to attach enough NEAR to cover the
+    /// storage, and the rest is refunded to the caller. The storage
+    /// deposit goes to the consumer when it unregisters.
+    #[payable]
     pub fn register_consumer(&mut self, account_id: ConsumerId) {
-        let consumer = Consumer {
-            account_id: account_id.clone(),
-            near_balance: NearToken::from_near(0),
-            near_balance_producer: LookupMap::new(StorageKey::ConsumerNearBalanceProducer {
-                consumer: account_id.clone(),
-            }),
-            ft_balances: LookupMap::new(StorageKey::ConsumerFtBalances {
-                consumer: account_id.clone(),
-            }),
-            ft_balances_producer: LookupMap::new(StorageKey::ConsumerFtBalancesProducers {
-                consumer: account_id.clone(),
-            }),
-            requests_succeeded: 0,
-            requests_timed_out: 0,
-        };
-        self.consumers.insert(account_id, consumer);
+        near_sdk::require!(
+            !self.consumers.contains_key(&account_id),
+            "Consumer is already registered"
+        );
+        let initial_storage_usage = env::storage_usage();
+        let consumer = Consumer::new(account_id.clone());
+        self.consumers.insert(account_id.clone(), consumer);
+        self.consumers.flush();
+        let storage_deposit = env::storage_byte_cost()
+            .saturating_mul((env::storage_usage() - initial_storage_usage) as u128);
+        near_sdk::require!(
+            env::attached_deposit() >= storage_deposit,
+            format!("Attach at least {storage_deposit} to pay for the consumer storage")
+        );
+        self.consumers.get_mut(&account_id).unwrap().storage_deposit = storage_deposit;
+
+        let refund = env::attached_deposit().saturating_sub(storage_deposit);
+        if !refund.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Removes the caller's consumer account, refunds all NEAR and
+    /// fungible token balances and the storage deposit. If the
+    /// consumer has requests in flight, this fails unless `force` is
+    /// set, in which case refunds for these requests are sent directly
+    /// to the consumer's account.
+    pub fn unregister_consumer(&mut self, force: bool) {
+        let account_id = env::predecessor_account_id();
+        let mut consumer = self
+            .consumers
+            .remove(&account_id)
+            .expect("Consumer is not registered");
+        near_sdk::require!(
+            force || consumer.requests_pending == 0,
+            "Consumer has pending requests, use force to unregister anyway"
+        );
+
+        let mut near_amount = consumer
+            .near_balance
+            .checked_add(consumer.storage_deposit)
+            .expect("Overflow");
+        for (_, near_balance) in consumer.near_balance_producer.drain() {
+            near_amount = near_amount.checked_add(near_balance).expect("Overflow");
+        }
+        let mut ft_amounts = BTreeMap::<FtId, u128>::new();
+        for (ft_id, ft_balance) in consumer.ft_balances.drain() {
+            let amount = ft_amounts.entry(ft_id).or_default();
+            *amount = amount.checked_add(ft_balance.0).expect("Overflow");
+        }
+        for ((_, ft_id), ft_balance) in consumer.ft_balances_producer.drain() {
+            let amount = ft_amounts.entry(ft_id).or_default();
+            *amount = amount.checked_add(ft_balance.0).expect("Overflow");
+        }
+        consumer.producer_usage.clear();
+        consumer.ft_spent.clear();
+
+        log!("Unregistered {account_id}, refunding {near_amount}");
+        if !near_amount.is_zero() {
+            Promise::new(account_id.clone()).transfer(near_amount);
+        }
+        for (ft_id, amount) in ft_amounts {
+            log!("Refunding {amount} {ft_id} to {account_id}");
+            ext_nep141::ext(ft_id)
+                .with_static_gas(Gas::from_tgas(10))
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .ft_transfer(account_id.clone(), U128(amount), None);
+        }
     }
 
     pub fn is_registered_as_consumer(&self, account_id: &ConsumerId) -> bool {
         self.consumers.contains_key(account_id)
     }
+
+    pub fn get_consumer_details(&self, account_id: ConsumerId) -> ConsumerDetails {
+        let consumer = self
+            .consumers
+            .get(&account_id)
+            .expect("Consumer is not registered");
+        ConsumerDetails {
+            account_id: consumer.account_id.clone(),
+            requests_succeeded: consumer.requests_succeeded,
+            requests_timed_out: consumer.requests_timed_out,
+            requests_rejected: consumer.requests_rejected,
+            requests_pending: consumer.requests_pending,
+            near_spent: consumer.near_spent,
+            ft_spent: consumer
+                .ft_spent
+                .iter()
+                .map(|(ft_id, amount)| (ft_id.clone(), *amount))
+                .collect(),
+            producers: consumer
+                .producer_usage
+                .iter()
+                .map(|(producer_id, usage)| ConsumerProducerDetails {
+                    producer_id: producer_id.clone(),
+                    requests_succeeded: usage.requests_succeeded,
+                    requests_timed_out: usage.requests_timed_out,
+                    requests_rejected: usage.requests_rejected,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    pub fn record_producer_usage(&mut self, consumer_id: &ConsumerId, producer_id: &ProducerId) {
+        let Some(consumer) = self.consumers.get_mut(consumer_id) else {
+            return;
+        };
+        let epoch_height = env::epoch_height();
+        if let Some(usage) = consumer.producer_usage.get_mut(producer_id) {
+            usage.record_request(epoch_height);
+        } else {
+            consumer.producer_usage.insert(
+                producer_id.clone(),
+                ProducerUsage {
+                    epoch_height,
+                    requests_this_epoch: 1,
+                    requests_previous_epoch: 0,
+                    requests_succeeded: 0,
+                    requests_timed_out: 0,
+                    requests_rejected: 0,
+                },
+            );
+        }
+    }
+
+    /// Updates the consumer's counters and spend once a request
+    /// is responded to, rejected or times out.
+    pub fn record_consumer_outcome(
+        &mut self,
+        consumer_id: &ConsumerId,
+        producer_id: &ProducerId,
+        status: RequestStatus,
+        fee: &PrepaidFee,
+        refund_amount: Option<U128>,
+    ) {
+        let Some(consumer) = self.consumers.get_mut(consumer_id) else {
+            return;
+        };
+        consumer.requests_pending = consumer.requests_pending.saturating_sub(1);
+        if !consumer.producer_usage.contains_key(producer_id) {
+            consumer.producer_usage.insert(
+                producer_id.clone(),
+                ProducerUsage {
+                    epoch_height: env::epoch_height(),
+                    requests_this_epoch: 0,
+                    requests_previous_epoch: 0,
+                    requests_succeeded: 0,
+                    requests_timed_out: 0,
+                    requests_rejected: 0,
+                },
+            );
+        }
+        let usage = consumer.producer_usage.get_mut(producer_id).unwrap();
+        match status {
+            RequestStatus::Pending => return,
+            RequestStatus::TimedOut => {
+                consumer.requests_timed_out += 1;
+                usage.requests_timed_out += 1;
+                return;
+            }
+            RequestStatus::Rejected => {
+                consumer.requests_rejected += 1;
+                usage.requests_rejected += 1;
+                return;
+            }
+            RequestStatus::Succeeded => {}
+        }
+        consumer.requests_succeeded += 1;
+        usage.requests_succeeded += 1;
+
+        if fee.is_sponsored() {
+            return;
+        }
+        let spent = fee
+            .amount()
+            .0
+            .saturating_sub(refund_amount.unwrap_or(U128(0)).0);
+        match fee {
+            PrepaidFee::None => {}
+            PrepaidFee::Near { .. } => {
+                consumer.near_spent = consumer
+                    .near_spent
+                    .checked_add(NearToken::from_yoctonear(spent))
+                    .expect("Overflow");
+            }
+            PrepaidFee::FungibleToken { token, .. } => {
+                let total = consumer.ft_spent.get(token).cloned().unwrap_or(U128(0));
+                consumer.ft_spent.insert(
+                    token.clone(),
+                    total.0.checked_add(spent).expect("Overflow").into(),
+                );
+            }
+        }
+    }
 }
 
 #[ext_contract(ext_oracle_consumer)]
 pub trait ConsumerExt {
-    fn request(&mut self, producer_id: ProducerId, request_data: String);
+    fn request(
+        &mut self,
+        producer_id: ProducerId,
+        request_data: String,
+        options: Option<RequestOptions>,
+    );
 }
 
 #[cfg(feature = "contract")]
 #[near]
 impl ConsumerExt for Oracle {
+    /// Requests data from a producer. Returns `Option<Response>`, or
+    /// the existing `IdempotentRequest` if `options.idempotency_key`
+    /// was already used, see `RequestOptions`.
     #[payable]
-    fn request(&mut self, producer_id: ProducerId, request_data: String) {
+    fn request(
+        &mut self,
+        producer_id: ProducerId,
+        request_data: String,
+        options: Option<RequestOptions>,
+    ) {
         let consumer_id = env::predecessor_account_id();
+        let options = options.unwrap_or_default();
+        if let Some(idempotency_key) = options.idempotency_key.as_ref() {
+            let request_hash = request_hash(&request_data, options.request_bytes.as_ref());
+            if let Some(existing) = self.find_idempotent_request(
+                &consumer_id,
+                idempotency_key,
+                &producer_id,
+                request_hash,
+            ) {
+                log!(
+                    "Request with idempotency key {idempotency_key} already exists: {request_id}",
+                    request_id = existing.request_id.0,
+                );
+                env::value_return(
+                    &serde_json::to_vec(existing).expect("Can't serialize idempotent request"),
+                );
+                if !env::attached_deposit().is_zero() {
+                    Promise::new(consumer_id).transfer(env::attached_deposit());
+                }
+                return;
+            }
+        }
+        let promise_idx = self.create_request(consumer_id, producer_id, request_data, options);
+        env::promise_return(promise_idx);
+    }
+}
+
+/// Optional arguments of `request`.
+#[derive(Clone, Default, Debug)]
+#[near(serializers=[json])]
+pub struct RequestOptions {
+    /// Receives the producer's referral share of the fee once the
+    /// producer responds. Can't be the consumer or the producer.
+    pub referrer: Option<AccountId>,
+    /// Added to the fee in the producer's fee token, paid on success
+    /// and refunded on timeout, so that producers can prioritize
+    /// requests with higher tips.
+    pub tip: Option<U128>,
+    /// Blob uploaded by the consumer with `upload_blob` that holds
+    /// data too large for `request_data`. The producer fetches it
+    /// with `get_blob(consumer_id, request_blob)`.
+    pub request_blob: Option<Base58CryptoHash>,
+    /// Binary data, instead of encoding it in `request_data`. Can't
+    /// be longer than 4096 bytes, larger data should be uploaded as a
+    /// blob.
+    pub request_bytes: Option<Base64VecU8>,
+    /// Opaque identifier of the consumer's user that the request is
+    /// made on behalf of, so that producers can rate limit, moderate
+    /// and bill individual users of a consumer. Can't be longer than
+    /// 1024 bytes.
+    pub end_user: Option<String>,
+    /// Method that the response is also delivered to, whether the
+    /// request succeeds or times out.
+    pub callback: Option<ResponseCallback>,
+    /// If a request with the same key was made in the last
+    /// `IDEMPOTENCY_WINDOW_BLOCKS` blocks, nothing is charged and the
+    /// existing `IdempotentRequest` is returned instead of
+    /// `Option<Response>`. Fails if that request was made to another
+    /// producer or with other data.
+    pub idempotency_key: Option<String>,
+    /// The producer can't respond after this many blocks, and anyone
+    /// can `expire` the request to get the fee refunded without
+    /// waiting for the yield timeout.
+    pub deadline_blocks: Option<BlockHeight>,
+    /// Gas reserved for settling the request once the producer
+    /// responds, see `get_request_gas_budget`.
+    pub on_response_gas: Option<Gas>,
+    /// Method that checks the response before the fee is paid, see
+    /// `ResponseValidator`.
+    pub validator: Option<ResponseValidator>,
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Charges the fee, yields until the producer responds and emits
+    /// the `request` event. Returns the promise that resolves to
+    /// `Option<Response>`.
+    pub fn create_request(
+        &mut self,
+        consumer_id: ConsumerId,
+        producer_id: ProducerId,
+        request_data: String,
+        options: RequestOptions,
+    ) -> PromiseIndex {
+        let RequestOptions {
+            referrer,
+            tip,
+            request_blob,
+            request_bytes,
+            end_user,
+            callback,
+            idempotency_key,
+            deadline_blocks,
+            on_response_gas,
+            validator,
+        } = options;
+        if deadline_blocks == Some(0) {
+            env::panic_str("Deadline can't be 0 blocks");
+        }
+        let deadline_block = deadline_blocks.map(|deadline_blocks| {
+            env::block_height()
+                .checked_add(deadline_blocks)
+                .unwrap_or_else(|| env::panic_str("Deadline is too far in the future"))
+        });
+        near_sdk::require!(
+            referrer.as_ref() != Some(&consumer_id) && referrer.as_ref() != Some(&producer_id),
+            "Referrer can't be the consumer or the producer"
+        );
+        if let Some(callback) = callback.as_ref() {
+            near_sdk::require!(
+                callback.contract_id != env::current_account_id(),
+                "Callback can't be the oracle contract"
+            );
+        }
+        if let Some(validator) = validator.as_ref() {
+            near_sdk::require!(
+                validator.contract_id != env::current_account_id(),
+                "Validator can't be the oracle contract"
+            );
+        }
+        if let Some(end_user) = end_user.as_ref() {
+            near_sdk::require!(
+                end_user.len() <= MAX_END_USER_LENGTH,
+                format!("End user can't be longer than {MAX_END_USER_LENGTH} bytes")
+            );
+        }
+        if let Some(request_bytes) = request_bytes.as_ref() {
+            near_sdk::require!(
+                request_bytes.0.len() <= MAX_REQUEST_BYTES_LENGTH,
+                format!(
+                    "Request bytes can't be longer than {MAX_REQUEST_BYTES_LENGTH} bytes, use request_blob instead"
+                )
+            );
+        }
         let producer = self
             .producers
             .get(&producer_id)
             .expect("Producer doesn't exist");
         let fee = producer.fee.clone();
         let send_callback = producer.send_callback;
-        if let Some(charged_fee) = self.try_charge_fee(&consumer_id, &producer_id, &fee) {
+        let max_extensions = producer.max_extensions;
+        let beneficiaries = producer.beneficiaries.clone();
+        if producer.liveness_threshold_blocks.is_some() && producer.is_online() != Some(true) {
+            env::panic_str("Producer is offline");
+        }
+        near_sdk::require!(
+            validator.is_none() || producer.accepts_validators,
+            "Producer doesn't accept validators"
+        );
+        let extra_gas = extra_on_response_gas(
+            callback.as_ref().map(|callback| callback.gas),
+            validator.as_ref().map(|validator| validator.gas),
+        )
+        .saturating_add(extension_gas(max_extensions));
+        let on_response_gas = self.on_response_gas(&producer_id, on_response_gas, extra_gas);
+        self.enforce_request_limits(&consumer_id, &producer_id);
+        if let Some(request_blob) = request_blob {
+            self.reference_blob(&consumer_id, request_blob);
+        }
+        if let Some(charged_fee) = self.try_charge_fee(&consumer_id, &producer_id, &fee, tip) {
+            self.record_producer_usage(&consumer_id, &producer_id);
+            if let Some(consumer) = self.consumers.get_mut(&consumer_id) {
+                consumer.requests_pending += 1;
+            }
             let request_id = self.next_request_id;
             self.next_request_id = self
                 .next_request_id
@@ -122,52 +753,73 @@ impl ConsumerExt for Oracle {
                 .checked_add(1)
                 .expect("Overflow")
                 .into();
+            if let Some(idempotency_key) = idempotency_key.clone() {
+                self.record_idempotent_request(
+                    &consumer_id,
+                    idempotency_key,
+                    producer_id.clone(),
+                    request_id,
+                    request_hash(&request_data, request_bytes.as_ref()),
+                );
+            }
 
             if send_callback {
                 ext_producer::ext(producer_id.clone()).on_request(
                     request_id,
                     request_data.clone(),
                     charged_fee.clone(),
+                    end_user.clone(),
+                    request_bytes.clone(),
+                    request_blob,
                 );
             }
 
-            let promise_idx = env::promise_yield_create(
-                "on_response",
-                &serde_json::to_vec(&serde_json::json!({
-                    "producer_id": producer_id,
-                    "request_id": request_id,
-                    "consumer_id": consumer_id,
-                    "fee": charged_fee,
-                }))
-                .unwrap(),
-                Gas::from_tgas(5),
-                GasWeight::default(),
-                RESUMPTION_TOKEN_REGISTER,
-            );
-            let resumption_token = if let Some(data) = env::read_register(RESUMPTION_TOKEN_REGISTER)
-            {
-                if let Ok(resumption_token) = ResumptionToken::try_from(data) {
-                    resumption_token
-                } else {
-                    env::panic_str("Wrong register length")
-                }
-            } else {
-                env::panic_str("Register is empty")
-            };
+            let created_at_block = env::block_height();
+            let (promise_idx, resumption_token) = yield_on_response(&RequestContext {
+                producer_id: producer_id.clone(),
+                request_id,
+                consumer_id: consumer_id.clone(),
+                fee: charged_fee,
+                referrer: referrer.clone(),
+                created_at_block,
+                request_blob,
+                callback,
+                idempotency_key,
+                on_response_gas,
+                validator,
+                extensions_left: max_extensions,
+                beneficiaries,
+            });
 
             self.producers
                 .get_mut(&producer_id)
                 .expect("Producer doesn't exist")
                 .requests_pending
-                .insert(request_id, PendingRequest { resumption_token });
+                .insert(
+                    request_id,
+                    PendingRequest {
+                        resumption_token,
+                        created_at_block,
+                        extensions: 0,
+                        max_extensions,
+                        progress: None,
+                        deadline_block,
+                    },
+                );
             OracleEvent::Request(RequestEventV1 {
                 producer_id,
                 consumer_id,
                 request_id,
                 request_data,
+                request_bytes,
+                referrer,
+                tip,
+                request_blob,
+                end_user,
+                deadline_blocks,
             })
             .emit();
-            env::promise_return(promise_idx);
+            promise_idx
         } else {
             env::panic_str("Not enough balance");
         }
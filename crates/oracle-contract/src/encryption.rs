@@ -0,0 +1,116 @@
+use near_sdk::{env, json_types::Base64VecU8, near, serde_json};
+
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// Length of X25519 public keys.
+const ENCRYPTION_KEY_LENGTH: usize = 32;
+
+/// Encrypted `request_data` or `Response::response_data`, serialized
+/// as JSON in place of the plain text. Uses X25519 key exchange with
+/// XSalsa20-Poly1305 (NaCl `crypto_box`). A request is sealed to the
+/// producer's `encryption_public_key`, and the response is sealed to
+/// the request's `ephemeral_public_key`, so only the consumer that
+/// kept the ephemeral secret key can open it.
+#[derive(Clone, PartialEq, Debug)]
+#[near(serializers=[json])]
+pub struct EncryptedEnvelope {
+    /// Public key of a one-time key pair generated by the sender.
+    pub ephemeral_public_key: Base64VecU8,
+    /// 24-byte nonce.
+    pub nonce: Base64VecU8,
+    /// Encrypted payload with a 16-byte authentication tag.
+    pub ciphertext: Base64VecU8,
+}
+
+impl EncryptedEnvelope {
+    /// Parses an envelope from `request_data` or `response_data`.
+    /// Returns `None` if the data is not an envelope.
+    pub fn from_data(data: &str) -> Option<Self> {
+        serde_json::from_str(data).ok()
+    }
+
+    /// Serializes the envelope to be sent as `request_data` or
+    /// `response_data`.
+    pub fn to_data(&self) -> String {
+        serde_json::to_string(self).expect("Failed to serialize envelope")
+    }
+}
+
+#[cfg(feature = "encryption")]
+pub use crypto_box::{PublicKey as EncryptionPublicKey, SecretKey as EncryptionSecretKey};
+
+#[cfg(feature = "encryption")]
+mod seal {
+    use crypto_box::{
+        aead::{Aead, AeadCore, OsRng},
+        Nonce, PublicKey, SalsaBox, SecretKey,
+    };
+
+    use super::EncryptedEnvelope;
+
+    impl EncryptedEnvelope {
+        /// Encrypts `plaintext` to `recipient`. Returns the envelope
+        /// and the ephemeral secret key, which consumers need to keep
+        /// to open the response with `open`.
+        pub fn seal(recipient: &PublicKey, plaintext: &[u8]) -> (Self, SecretKey) {
+            let ephemeral_secret_key = SecretKey::generate(&mut OsRng);
+            let salsa_box = SalsaBox::new(recipient, &ephemeral_secret_key);
+            let nonce = SalsaBox::generate_nonce(&mut OsRng);
+            let ciphertext = salsa_box
+                .encrypt(&nonce, plaintext)
+                .expect("Failed to encrypt");
+            let envelope = Self {
+                ephemeral_public_key: ephemeral_secret_key.public_key().to_bytes().to_vec().into(),
+                nonce: nonce.to_vec().into(),
+                ciphertext: ciphertext.into(),
+            };
+            (envelope, ephemeral_secret_key)
+        }
+
+        /// Encrypts a response to the sender of this request envelope.
+        /// Used by nodes.
+        pub fn seal_reply(&self, plaintext: &[u8]) -> Option<Self> {
+            let recipient = PublicKey::from_slice(&self.ephemeral_public_key.0).ok()?;
+            Some(Self::seal(&recipient, plaintext).0)
+        }
+
+        /// Decrypts the envelope. Nodes use the secret key of their
+        /// published `encryption_public_key`, consumers use the
+        /// ephemeral secret key returned by `seal`. Returns `None` if
+        /// the envelope is malformed or can't be decrypted with this
+        /// key.
+        pub fn open(&self, secret_key: &SecretKey) -> Option<Vec<u8>> {
+            let sender = PublicKey::from_slice(&self.ephemeral_public_key.0).ok()?;
+            if self.nonce.0.len() != 24 {
+                return None;
+            }
+            let nonce = Nonce::from_slice(&self.nonce.0);
+            SalsaBox::new(&sender, secret_key)
+                .decrypt(nonce, self.ciphertext.0.as_slice())
+                .ok()
+        }
+    }
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Publishes the X25519 public key that consumers can encrypt
+    /// requests to. `None` removes the key.
+    pub fn set_encryption_key(&mut self, encryption_public_key: Option<Base64VecU8>) {
+        if let Some(key) = encryption_public_key.as_ref() {
+            near_sdk::require!(
+                key.0.len() == ENCRYPTION_KEY_LENGTH,
+                format!("Encryption key must be {ENCRYPTION_KEY_LENGTH} bytes long")
+            );
+        }
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.encryption_public_key = encryption_public_key;
+
+        Oracle::emit_producer_updated(producer);
+    }
+}
@@ -1,14 +1,17 @@
-use near_sdk::{env, json_types::U128, near, store::LookupMap, Gas, NearToken, Promise};
+use near_sdk::{env, json_types::U128, near, AccountId, Gas, NearToken, Promise};
 use near_sdk_contract_tools::ft::ext_nep141;
 
 use crate::{
     balance::FtId,
     consumer::{ConsumerId, OracleEvent},
-    producer::{Producer, ProducerId},
+    producer::ProducerId,
 };
 #[cfg(feature = "contract")]
 use crate::{Oracle, OracleExt};
 
+const MAX_BENEFICIARIES: usize = 10;
+const MAX_VOLUME_TIERS: usize = 10;
+
 /// Fees are set by producers. If the consumer's balance is less
 /// than prepaid_amount, the request will be rejected without a log generated.
 /// The producer may choose to refund a part of the fee if the request
@@ -21,6 +24,27 @@ pub enum ProducerFee {
     FungibleToken { token: FtId, prepaid_amount: U128 },
 }
 
+/// A discount that applies to consumers who made at least
+/// `min_requests` requests to the producer in the current and the
+/// previous epoch.
+#[derive(Clone, PartialEq, Debug)]
+#[near(serializers=[borsh, json])]
+pub struct VolumeTier {
+    pub min_requests: u64,
+    /// Discount in basis points, 10000 is a 100% discount.
+    pub discount_bps: u16,
+}
+
+/// An account that receives a share of the producer's earnings.
+#[derive(Clone, PartialEq, Debug)]
+#[near(serializers=[borsh, json])]
+pub struct Beneficiary {
+    pub account_id: AccountId,
+    /// Share of the payout in basis points. Shares of all
+    /// beneficiaries add up to 10000.
+    pub share_bps: u16,
+}
+
 /// A fee that the consumer has paid for a request. Can be partially
 /// refunded by the producer in response.
 #[near(serializers=[json, borsh])]
@@ -38,12 +62,53 @@ pub enum PrepaidFee {
     },
 }
 
+impl PrepaidFee {
+    pub fn amount(&self) -> U128 {
+        match self {
+            PrepaidFee::None => U128(0),
+            PrepaidFee::Near { amount, .. } => U128(amount.as_yoctonear()),
+            PrepaidFee::FungibleToken { amount, .. } => *amount,
+        }
+    }
+
+    /// Whether the fee was taken from one of the consumer's balances.
+    pub fn is_paid_from_balance(&self) -> bool {
+        matches!(
+            self,
+            PrepaidFee::Near {
+                payment_type: NearPaymentType::ForSpecificProducer
+                    | NearPaymentType::ForAllProducers,
+                ..
+            } | PrepaidFee::FungibleToken {
+                payment_type: FtPaymentType::ForSpecificProducer | FtPaymentType::ForAllProducers,
+                ..
+            }
+        )
+    }
+
+    /// Whether the fee was paid from the producer's sponsorship pool.
+    pub fn is_sponsored(&self) -> bool {
+        matches!(
+            self,
+            PrepaidFee::Near {
+                payment_type: NearPaymentType::Sponsored,
+                ..
+            } | PrepaidFee::FungibleToken {
+                payment_type: FtPaymentType::Sponsored,
+                ..
+            }
+        )
+    }
+}
+
 #[near(serializers=[json, borsh])]
 #[derive(Debug, Clone, PartialEq)]
 pub enum NearPaymentType {
     ForSpecificProducer,
     ForAllProducers,
     AttachedToCall,
+    /// Paid from the producer's sponsorship pool.
+    Sponsored,
 }
 
 #[near(serializers=[json, borsh])]
@@ -51,6 +116,8 @@ pub enum NearPaymentType {
 pub enum FtPaymentType {
     ForSpecificProducer,
     ForAllProducers,
+    /// Paid from the producer's sponsorship pool.
+    Sponsored,
 }
 
 #[cfg(feature = "contract")]
@@ -68,29 +135,150 @@ impl Oracle {
             .expect("Producer is not registered")
             .fee = fee;
         let producer = self.producers.get(&env::predecessor_account_id()).unwrap();
-        OracleEvent::ProducerCreated(Producer {
-            account_id: producer.account_id.clone(),
-            requests_succeded: producer.requests_succeded,
-            requests_timed_out: producer.requests_timed_out,
-            requests_pending: LookupMap::new(b"dontcare".as_slice()),
-            fee: producer.fee.clone(),
-            send_callback: producer.send_callback,
-            name: producer.name.clone(),
-            description: producer.description.clone(),
-            example_input: producer.example_input.clone(),
-        })
-        .emit();
+        OracleEvent::ProducerCreated(producer.event_copy()).emit();
+    }
+
+    pub fn set_volume_tiers(&mut self, mut volume_tiers: Vec<VolumeTier>) {
+        near_sdk::require!(
+            volume_tiers.len() <= MAX_VOLUME_TIERS,
+            format!("Can't have more than {MAX_VOLUME_TIERS} volume tiers")
+        );
+        near_sdk::require!(
+            volume_tiers.iter().all(|tier| tier.discount_bps <= 10000),
+            "Discount can't be more than 10000 basis points"
+        );
+        volume_tiers.sort_by_key(|tier| tier.min_requests);
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.volume_tiers = volume_tiers;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    /// Sets the accounts that the producer's earnings are split
+    /// between. An empty list sends everything to the producer.
+    pub fn set_beneficiaries(&mut self, beneficiaries: Vec<Beneficiary>) {
+        near_sdk::require!(
+            beneficiaries.len() <= MAX_BENEFICIARIES,
+            format!("Can't have more than {MAX_BENEFICIARIES} beneficiaries")
+        );
+        near_sdk::require!(
+            beneficiaries.is_empty()
+                || beneficiaries
+                    .iter()
+                    .map(|beneficiary| beneficiary.share_bps as u32)
+                    .sum::<u32>()
+                    == 10000,
+            "Shares must add up to 10000 basis points"
+        );
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.beneficiaries = beneficiaries;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    /// Returns the volume tier that currently applies to requests
+    /// from the consumer to the producer.
+    pub fn get_volume_tier(
+        &self,
+        producer_id: ProducerId,
+        consumer_id: ConsumerId,
+    ) -> Option<VolumeTier> {
+        self.volume_tier(&consumer_id, &producer_id)
+    }
+
+    /// Returns the fee the consumer will be charged for the next
+    /// request to the producer, with the volume discount applied.
+    pub fn get_fee_for_consumer(
+        &self,
+        producer_id: ProducerId,
+        consumer_id: ConsumerId,
+    ) -> Option<ProducerFee> {
+        self.producers
+            .get(&producer_id)
+            .map(|producer| self.discounted_fee(&consumer_id, &producer_id, &producer.fee))
     }
 }
 
 #[cfg(feature = "contract")]
 impl Oracle {
+    pub fn volume_tier(
+        &self,
+        consumer_id: &ConsumerId,
+        producer_id: &ProducerId,
+    ) -> Option<VolumeTier> {
+        let producer = self.producers.get(producer_id)?;
+        let requests = self
+            .consumers
+            .get(consumer_id)?
+            .producer_usage
+            .get(producer_id)
+            .map(|usage| usage.rolling_requests(env::epoch_height()))
+            .unwrap_or(0);
+        producer
+            .volume_tiers
+            .iter()
+            .rev()
+            .find(|tier| requests >= tier.min_requests)
+            .cloned()
+    }
+
+    pub fn discounted_fee(
+        &self,
+        consumer_id: &ConsumerId,
+        producer_id: &ProducerId,
+        fee: &ProducerFee,
+    ) -> ProducerFee {
+        let Some(tier) = self.volume_tier(consumer_id, producer_id) else {
+            return fee.clone();
+        };
+        let multiplier = 10000 - tier.discount_bps as u128;
+        let discount = |amount: u128| apply_bps(amount, multiplier);
+        match fee {
+            ProducerFee::None => ProducerFee::None,
+            ProducerFee::Near { prepaid_amount } => ProducerFee::Near {
+                prepaid_amount: NearToken::from_yoctonear(discount(prepaid_amount.as_yoctonear())),
+            },
+            ProducerFee::FungibleToken {
+                token,
+                prepaid_amount,
+            } => ProducerFee::FungibleToken {
+                token: token.clone(),
+                prepaid_amount: discount(prepaid_amount.0).into(),
+            },
+        }
+    }
+
     pub fn try_charge_fee(
         &mut self,
         consumer_id: &ConsumerId,
         producer_id: &ProducerId,
         fee: &ProducerFee,
+        tip: Option<U128>,
     ) -> Option<PrepaidFee> {
+        let mut fee = self.discounted_fee(consumer_id, producer_id, fee);
+        let tip = tip.unwrap_or(U128(0)).0;
+        if tip != 0 {
+            match &mut fee {
+                ProducerFee::None => {
+                    env::panic_str("Producer doesn't charge a fee, so it can't be tipped")
+                }
+                ProducerFee::Near { prepaid_amount } => {
+                    *prepaid_amount = prepaid_amount
+                        .checked_add(NearToken::from_yoctonear(tip))
+                        .expect("Overflow");
+                }
+                ProducerFee::FungibleToken { prepaid_amount, .. } => {
+                    *prepaid_amount = prepaid_amount.0.checked_add(tip).expect("Overflow").into();
+                }
+            }
+        }
+        let fee = &fee;
         match fee {
             ProducerFee::None => {
                 if !env::attached_deposit().is_zero() {
@@ -100,15 +288,29 @@ impl Oracle {
             }
             ProducerFee::Near { prepaid_amount } => {
                 if !env::attached_deposit().is_zero() {
-                    if env::attached_deposit() == *prepaid_amount {
-                        return Some(PrepaidFee::Near {
-                            amount: env::attached_deposit(),
-                            payment_type: NearPaymentType::AttachedToCall,
-                        });
-                    } else {
-                        env::panic_str(
-                            "Attached NEAR deposit is not equal to the requested amount",
-                        );
+                    // The discount applies here too, the rest of the deposit is refunded
+                    near_sdk::require!(
+                        env::attached_deposit() >= *prepaid_amount,
+                        "Attached NEAR deposit is less than the requested amount"
+                    );
+                    let excess = env::attached_deposit()
+                        .checked_sub(*prepaid_amount)
+                        .unwrap();
+                    if !excess.is_zero() {
+                        Promise::new(consumer_id.clone()).transfer(excess);
+                    }
+                    return Some(PrepaidFee::Near {
+                        amount: *prepaid_amount,
+                        payment_type: NearPaymentType::AttachedToCall,
+                    });
+                }
+
+                // Tips are paid by the consumer, never by the sponsorship pool
+                if tip == 0 {
+                    if let Some(prepaid_fee) =
+                        self.try_charge_sponsored(consumer_id, producer_id, fee)
+                    {
+                        return Some(prepaid_fee);
                     }
                 }
 
@@ -145,6 +347,15 @@ impl Oracle {
                 token,
                 prepaid_amount,
             } => {
+                // Tips are paid by the consumer, never by the sponsorship pool
+                if tip == 0 {
+                    if let Some(prepaid_fee) =
+                        self.try_charge_sponsored(consumer_id, producer_id, fee)
+                    {
+                        return Some(prepaid_fee);
+                    }
+                }
+
                 let consumer = self
                     .consumers
                     .get_mut(consumer_id)
@@ -195,6 +406,10 @@ impl Oracle {
         fee: &PrepaidFee,
         refund_amount: U128,
     ) {
+        if fee.is_paid_from_balance() && !self.consumers.contains_key(consumer_id) {
+            self.refund_to_account(consumer_id, fee, refund_amount);
+            return;
+        }
         match fee {
             PrepaidFee::None => {}
             PrepaidFee::Near {
@@ -234,6 +449,12 @@ impl Oracle {
                     Promise::new(consumer_id.clone())
                         .transfer(NearToken::from_yoctonear(refund_amount.0));
                 }
+                NearPaymentType::Sponsored => {
+                    self.credit_sponsorship_near(
+                        producer_id,
+                        NearToken::from_yoctonear(refund_amount.0),
+                    );
+                }
             },
             PrepaidFee::FungibleToken {
                 token,
@@ -269,6 +490,9 @@ impl Oracle {
                         consumer.ft_balances.insert(token.clone(), refund_amount);
                     }
                 }
+                FtPaymentType::Sponsored => {
+                    self.credit_sponsorship_ft(producer_id, token, refund_amount);
+                }
             },
         }
     }
@@ -279,6 +503,10 @@ impl Oracle {
         producer_id: &ProducerId,
         fee: &PrepaidFee,
     ) {
+        if fee.is_paid_from_balance() && !self.consumers.contains_key(consumer_id) {
+            self.refund_to_account(consumer_id, fee, fee.amount());
+            return;
+        }
         match fee {
             PrepaidFee::None => {}
             PrepaidFee::Near {
@@ -311,6 +539,10 @@ impl Oracle {
                 NearPaymentType::AttachedToCall => {
                     Promise::new(consumer_id.clone()).transfer(*amount);
                 }
+                NearPaymentType::Sponsored => {
+                    self.credit_sponsorship_near(producer_id, *amount);
+                    self.restore_sponsored_usage(consumer_id, producer_id);
+                }
             },
             PrepaidFee::FungibleToken {
                 token,
@@ -346,26 +578,60 @@ impl Oracle {
                         consumer.ft_balances.insert(token.clone(), *amount);
                     }
                 }
+                FtPaymentType::Sponsored => {
+                    self.credit_sponsorship_ft(producer_id, token, *amount);
+                    self.restore_sponsored_usage(consumer_id, producer_id);
+                }
             },
         }
     }
 
+    /// Sends a refund directly to the consumer's account. Used when
+    /// the consumer has unregistered while the request was in flight.
+    pub fn refund_to_account(&self, consumer_id: &ConsumerId, fee: &PrepaidFee, amount: U128) {
+        match fee {
+            PrepaidFee::None => {}
+            PrepaidFee::Near { .. } => {
+                Promise::new(consumer_id.clone()).transfer(NearToken::from_yoctonear(amount.0));
+            }
+            PrepaidFee::FungibleToken { token, .. } => {
+                ext_nep141::ext(token.clone())
+                    .with_static_gas(Gas::from_tgas(10))
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .ft_transfer(consumer_id.clone(), amount, None);
+            }
+        }
+    }
+
+    /// Pays the fee that the producer keeps to `beneficiaries`, the
+    /// snapshot taken when the request was made.
     pub fn deposit_to_producer(
         &mut self,
         producer_id: ProducerId,
+        beneficiaries: &[Beneficiary],
         fee: &PrepaidFee,
         refund_amount: Option<U128>,
+        commission: U128,
     ) {
+        let withheld_amount = refund_amount
+            .unwrap_or(U128(0))
+            .0
+            .checked_add(commission.0)
+            .expect("Overflow");
         match fee {
             PrepaidFee::None => {}
             PrepaidFee::Near {
                 amount,
                 payment_type: _,
             } => {
-                if let Some(deposit_amount) = amount.checked_sub(NearToken::from_yoctonear(
-                    refund_amount.unwrap_or(U128(0)).0,
-                )) {
-                    Promise::new(producer_id.clone()).transfer(deposit_amount);
+                if let Some(deposit_amount) =
+                    amount.checked_sub(NearToken::from_yoctonear(withheld_amount))
+                {
+                    for (account_id, payout) in
+                        split_payout(&producer_id, beneficiaries, deposit_amount.as_yoctonear())
+                    {
+                        Promise::new(account_id).transfer(NearToken::from_yoctonear(payout));
+                    }
                 } else {
                     env::panic_str("Refund amount is greater than prepaid amount")
                 }
@@ -375,14 +641,16 @@ impl Oracle {
                 amount,
                 payment_type: _,
             } => {
-                if let Some(deposit_amount) =
-                    amount.0.checked_sub(refund_amount.unwrap_or(U128(0)).0)
-                {
-                    // TODO handle when the token is not registered in producer's account
-                    ext_nep141::ext(token.clone())
-                        .with_static_gas(Gas::from_tgas(10))
-                        .with_attached_deposit(NearToken::from_yoctonear(1))
-                        .ft_transfer(producer_id.clone(), deposit_amount.into(), None);
+                if let Some(deposit_amount) = amount.0.checked_sub(withheld_amount) {
+                    for (account_id, payout) in
+                        split_payout(&producer_id, beneficiaries, deposit_amount)
+                    {
+                        // TODO handle when the token is not registered in producer's account
+                        ext_nep141::ext(token.clone())
+                            .with_static_gas(Gas::from_tgas(10))
+                            .with_attached_deposit(NearToken::from_yoctonear(1))
+                            .ft_transfer(account_id, payout.into(), None);
+                    }
                 } else {
                     env::panic_str("Refund amount is greater than prepaid amount")
                 }
@@ -390,3 +658,33 @@ impl Oracle {
         }
     }
 }
+
+/// Returns `bps` basis points of `amount`, rounded down.
+pub fn apply_bps(amount: u128, bps: u128) -> u128 {
+    // Split the multiplication so that it can't overflow
+    amount / 10000 * bps + amount % 10000 * bps / 10000
+}
+
+/// Splits a payout between `beneficiaries`. If there are none,
+/// everything goes to the producer. The rounding remainder goes to
+/// the first beneficiary. Zero payouts are skipped.
+pub fn split_payout(
+    producer_id: &ProducerId,
+    beneficiaries: &[Beneficiary],
+    amount: u128,
+) -> Vec<(AccountId, u128)> {
+    if beneficiaries.is_empty() {
+        return vec![(producer_id.clone(), amount)];
+    }
+    let mut payouts = beneficiaries
+        .iter()
+        .map(|beneficiary| {
+            let payout = apply_bps(amount, beneficiary.share_bps as u128);
+            (beneficiary.account_id.clone(), payout)
+        })
+        .collect::<Vec<_>>();
+    let distributed = payouts.iter().map(|(_, payout)| payout).sum::<u128>();
+    payouts[0].1 += amount - distributed;
+    payouts.retain(|(_, payout)| *payout > 0);
+    payouts
+}
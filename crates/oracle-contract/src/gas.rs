@@ -0,0 +1,133 @@
+use near_sdk::{near, Gas};
+
+use crate::{fees::ProducerFee, producer::ProducerId};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// Gas for `on_response` when the fee doesn't need any cross-contract
+/// transfers.
+pub const MIN_ON_RESPONSE_GAS: Gas = Gas::from_tgas(5);
+/// Maximum gas of the yielded `on_response` callback, including the
+/// consumer's `ResponseCallback` and `ResponseValidator`.
+pub const MAX_ON_RESPONSE_GAS: Gas = Gas::from_tgas(200);
+/// Gas for `on_response` to call the consumer's validator, on top of
+/// the validator's own gas.
+pub const VALIDATION_GAS: Gas = Gas::from_tgas(5);
+/// Gas for `on_response` to extend a request, reserved up front for
+/// each extension that the producer allows.
+pub const EXTENSION_GAS: Gas = Gas::from_tgas(5);
+/// Gas used by `request` itself, without the yielded callback.
+pub const REQUEST_GAS: Gas = Gas::from_tgas(10);
+/// Gas of each `ft_transfer` that `on_response` makes to pay the
+/// producer's beneficiaries or refund the consumer.
+const FT_TRANSFER_GAS: Gas = Gas::from_tgas(10);
+
+/// Gas needed to make a request to a producer, returned by
+/// `get_request_gas_budget`.
+#[near(serializers=[json])]
+pub struct RequestGasBudget {
+    /// Smallest `on_response_gas` accepted by `request`. This is the
+    /// default if `on_response_gas` is not set.
+    pub min_on_response_gas: Gas,
+    /// Largest `on_response_gas` plus the callback and validator gas
+    /// accepted by `request`, after the gas reserved for the
+    /// producer's `max_extensions`.
+    pub max_on_response_gas: Gas,
+    /// Gas to attach to `request` with the minimum `on_response_gas`,
+    /// the given callback and validator gas and the gas reserved for
+    /// extensions. Gas for the consumer's own `.then()` comes on top
+    /// of this. Beneficiaries and `max_extensions` of the producer are
+    /// snapshotted when the request is made, so this can change if
+    /// the producer changes them before that.
+    pub request_gas: Gas,
+}
+
+/// Gas that the yielded `on_response` callback needs on top of
+/// `on_response_gas` for the consumer's callback and validator.
+pub fn extra_on_response_gas(callback_gas: Option<Gas>, validator_gas: Option<Gas>) -> Gas {
+    callback_gas.unwrap_or(Gas::from_gas(0)).saturating_add(
+        validator_gas.map_or(Gas::from_gas(0), |validator_gas| {
+            VALIDATION_GAS.saturating_add(validator_gas)
+        }),
+    )
+}
+
+/// Gas that the yielded `on_response` callback reserves for the
+/// extensions that are left, see `EXTENSION_GAS`.
+pub fn extension_gas(extensions_left: u32) -> Gas {
+    EXTENSION_GAS.saturating_mul(extensions_left as u64)
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    pub fn get_request_gas_budget(
+        &self,
+        producer_id: ProducerId,
+        callback_gas: Option<Gas>,
+        validator_gas: Option<Gas>,
+    ) -> RequestGasBudget {
+        let min_on_response_gas = self.min_on_response_gas(&producer_id);
+        let extension_gas = extension_gas(
+            self.producers
+                .get(&producer_id)
+                .expect("Producer doesn't exist")
+                .max_extensions,
+        );
+        RequestGasBudget {
+            min_on_response_gas,
+            max_on_response_gas: MAX_ON_RESPONSE_GAS.saturating_sub(extension_gas),
+            request_gas: REQUEST_GAS
+                .saturating_add(min_on_response_gas)
+                .saturating_add(extra_on_response_gas(callback_gas, validator_gas))
+                .saturating_add(extension_gas),
+        }
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Gas that `on_response` needs to settle the producer's fee. Fees
+    /// in fungible tokens are paid out with an `ft_transfer` to each
+    /// beneficiary, and can be refunded with another one.
+    pub fn min_on_response_gas(&self, producer_id: &ProducerId) -> Gas {
+        let producer = self
+            .producers
+            .get(producer_id)
+            .expect("Producer doesn't exist");
+        match producer.fee {
+            ProducerFee::None | ProducerFee::Near { .. } => MIN_ON_RESPONSE_GAS,
+            ProducerFee::FungibleToken { .. } => {
+                let transfers = producer.beneficiaries.len().max(1) as u64 + 1;
+                MIN_ON_RESPONSE_GAS.saturating_add(FT_TRANSFER_GAS.saturating_mul(transfers))
+            }
+        }
+    }
+
+    /// Checks `on_response_gas` requested by the consumer and returns
+    /// the gas to reserve for settling the request.
+    pub fn on_response_gas(
+        &self,
+        producer_id: &ProducerId,
+        on_response_gas: Option<Gas>,
+        extra_gas: Gas,
+    ) -> Gas {
+        let min_on_response_gas = self.min_on_response_gas(producer_id);
+        let on_response_gas = on_response_gas.unwrap_or(min_on_response_gas);
+        near_sdk::require!(
+            on_response_gas >= min_on_response_gas,
+            format!(
+                "on_response_gas can't be less than {} TGas",
+                min_on_response_gas.as_tgas()
+            )
+        );
+        near_sdk::require!(
+            on_response_gas.saturating_add(extra_gas) <= MAX_ON_RESPONSE_GAS,
+            format!(
+                "on_response_gas with callback, validator and extension gas can't be more than {} TGas",
+                MAX_ON_RESPONSE_GAS.as_tgas()
+            )
+        );
+        on_response_gas
+    }
+}
@@ -0,0 +1,205 @@
+use near_sdk::{
+    borsh, env,
+    json_types::{Base58CryptoHash, Base64VecU8},
+    near,
+    store::LookupMap,
+    BlockHeight, IntoStorageKey,
+};
+
+use crate::{
+    consumer::{ConsumerId, RequestId},
+    producer::ProducerId,
+};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// Number of blocks during which a repeated request with the same
+/// idempotency key returns the existing request instead of creating
+/// a new one.
+pub const IDEMPOTENCY_WINDOW_BLOCKS: BlockHeight = 1000;
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 64;
+/// Number of expired idempotency keys removed with each new one, so
+/// that they are removed faster than they are added.
+const EXPIRED_KEYS_REMOVED_PER_REQUEST: u32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[near(serializers=[borsh, json])]
+pub enum RequestStatus {
+    Pending,
+    Succeeded,
+    TimedOut,
+    /// The consumer's validator rejected the response.
+    Rejected,
+}
+
+/// Request made with an idempotency key. Returned by `request`
+/// instead of `Option<Response>` when the key is reused.
+#[derive(Clone, PartialEq, Debug)]
+#[near(serializers=[borsh, json])]
+pub struct IdempotentRequest {
+    pub producer_id: ProducerId,
+    pub request_id: RequestId,
+    pub created_at_block: BlockHeight,
+    pub status: RequestStatus,
+    /// sha256 of `request_data` and `request_bytes`, to reject a key
+    /// that is reused for a different request.
+    pub request_hash: Base58CryptoHash,
+}
+
+impl IdempotentRequest {
+    fn is_expired(&self) -> bool {
+        env::block_height().saturating_sub(self.created_at_block) >= IDEMPOTENCY_WINDOW_BLOCKS
+    }
+}
+
+/// Idempotency keys in the order they were used, so that expired
+/// ones can be removed without iterating over all of them.
+#[near(serializers=[borsh])]
+pub struct IdempotencyKeyQueue {
+    keys: LookupMap<u64, (ConsumerId, String, RequestId)>,
+    head: u64,
+    tail: u64,
+}
+
+impl IdempotencyKeyQueue {
+    pub fn new(prefix: impl IntoStorageKey) -> Self {
+        Self {
+            keys: LookupMap::new(prefix),
+            head: 0,
+            tail: 0,
+        }
+    }
+
+    fn push_back(&mut self, key: (ConsumerId, String, RequestId)) {
+        self.keys.insert(self.tail, key);
+        self.tail += 1;
+    }
+
+    fn front(&self) -> Option<&(ConsumerId, String, RequestId)> {
+        self.keys.get(&self.head)
+    }
+
+    fn pop_front(&mut self) {
+        self.keys.remove(&self.head);
+        self.head += 1;
+    }
+}
+
+/// Hash of the request that an idempotency key is used for.
+pub fn request_hash(request_data: &str, request_bytes: Option<&Base64VecU8>) -> Base58CryptoHash {
+    let data = borsh::to_vec(&(request_data, request_bytes.map(|bytes| &bytes.0)))
+        .expect("Can't serialize request");
+    env::sha256_array(&data).into()
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    pub fn get_idempotent_request(
+        &self,
+        consumer_id: ConsumerId,
+        idempotency_key: String,
+    ) -> Option<&IdempotentRequest> {
+        self.idempotent_requests
+            .get(&(consumer_id, idempotency_key))
+            .filter(|request| !request.is_expired())
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Returns the request made with this key, if it was made less
+    /// than `IDEMPOTENCY_WINDOW_BLOCKS` blocks ago, and removes it if
+    /// it's expired. Panics if the key was used for a request to
+    /// another producer or with other data.
+    pub fn find_idempotent_request(
+        &mut self,
+        consumer_id: &ConsumerId,
+        idempotency_key: &str,
+        producer_id: &ProducerId,
+        request_hash: Base58CryptoHash,
+    ) -> Option<&IdempotentRequest> {
+        let key = (consumer_id.clone(), idempotency_key.to_string());
+        if self.idempotent_requests.get(&key)?.is_expired() {
+            self.idempotent_requests.remove(&key);
+            return None;
+        }
+        let request = self.idempotent_requests.get(&key)?;
+        near_sdk::require!(
+            request.producer_id == *producer_id && request.request_hash == request_hash,
+            "Idempotency key was used for a different request"
+        );
+        Some(request)
+    }
+
+    /// Remembers a new request, replacing the previous request with
+    /// the same key if its window has passed, and removes a few
+    /// expired keys.
+    pub fn record_idempotent_request(
+        &mut self,
+        consumer_id: &ConsumerId,
+        idempotency_key: String,
+        producer_id: ProducerId,
+        request_id: RequestId,
+        request_hash: Base58CryptoHash,
+    ) {
+        near_sdk::require!(
+            idempotency_key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH,
+            format!("Idempotency key can't be longer than {MAX_IDEMPOTENCY_KEY_LENGTH} bytes")
+        );
+        self.idempotent_requests.insert(
+            (consumer_id.clone(), idempotency_key.clone()),
+            IdempotentRequest {
+                producer_id,
+                request_id,
+                created_at_block: env::block_height(),
+                status: RequestStatus::Pending,
+                request_hash,
+            },
+        );
+        self.idempotency_key_queue
+            .push_back((consumer_id.clone(), idempotency_key, request_id));
+        self.remove_expired_idempotency_keys();
+    }
+
+    /// Removes up to `EXPIRED_KEYS_REMOVED_PER_REQUEST` of the oldest
+    /// keys if they are expired. Keys that were reused since then are
+    /// skipped, they are queued again with the newer request.
+    fn remove_expired_idempotency_keys(&mut self) {
+        for _ in 0..EXPIRED_KEYS_REMOVED_PER_REQUEST {
+            let Some((consumer_id, idempotency_key, request_id)) =
+                self.idempotency_key_queue.front().cloned()
+            else {
+                return;
+            };
+            let key = (consumer_id, idempotency_key);
+            if let Some(request) = self.idempotent_requests.get(&key) {
+                if request.request_id == request_id {
+                    if !request.is_expired() {
+                        return;
+                    }
+                    self.idempotent_requests.remove(&key);
+                }
+            }
+            self.idempotency_key_queue.pop_front();
+        }
+    }
+
+    pub fn set_idempotent_request_status(
+        &mut self,
+        consumer_id: &ConsumerId,
+        idempotency_key: String,
+        request_id: RequestId,
+        status: RequestStatus,
+    ) {
+        if let Some(request) = self
+            .idempotent_requests
+            .get_mut(&(consumer_id.clone(), idempotency_key))
+        {
+            // The key could have been reused for a newer request
+            if request.request_id == request_id {
+                request.status = status;
+            }
+        }
+    }
+}
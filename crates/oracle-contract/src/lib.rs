@@ -1,13 +1,28 @@
 #![cfg_attr(not(feature = "contract"), allow(unused_imports, dead_code))]
 
 pub mod balance;
+pub mod blobs;
 pub mod consumer;
+pub mod encryption;
 pub mod fees;
+pub mod gas;
+pub mod idempotency;
+pub mod limits;
+pub mod liveness;
+pub mod migration;
+pub mod pipeline;
 pub mod producer;
+pub mod referral;
+pub mod sponsorship;
 
+use balance::FtId;
+use blobs::Blob;
 use consumer::{Consumer, ConsumerId, RequestId};
-use near_sdk::{near, store::LookupMap, BorshStorageKey};
-use producer::{Producer, ProducerId};
+use idempotency::{IdempotencyKeyQueue, IdempotentRequest};
+use near_sdk::{
+    json_types::U128, near, store::LookupMap, AccountId, BorshStorageKey, CryptoHash, NearToken,
+};
+use producer::{LatencyWindow, Producer, ProducerId};
 
 #[derive(BorshStorageKey)]
 #[near(serializers=[borsh])]
@@ -30,6 +45,28 @@ enum StorageKey {
         consumer: ConsumerId,
         producer: ProducerId,
     },
+    ProducerSponsorshipFtBalances {
+        producer: ProducerId,
+    },
+    ProducerSponsoredUsage {
+        producer: ProducerId,
+    },
+    ConsumerProducerUsage {
+        consumer: ConsumerId,
+    },
+    TransferredProducers,
+    ReferralEarningsNear,
+    ReferralEarningsFt,
+    ConsumerFtSpent {
+        consumer: ConsumerId,
+    },
+    ProducerRateLimitUsage {
+        producer: ProducerId,
+    },
+    Blobs,
+    IdempotentRequests,
+    LatencyWindows,
+    IdempotencyKeyQueue,
 }
 
 // TODO: Storage management
@@ -39,6 +76,21 @@ pub struct Oracle {
     producers: LookupMap<ProducerId, Producer>,
     consumers: LookupMap<ConsumerId, Consumer>,
     next_request_id: RequestId,
+    /// Old account IDs of producers that were transferred, mapped
+    /// to the account they were transferred to.
+    transferred_producers: LookupMap<ProducerId, ProducerId>,
+    /// NEAR earned by referrers, ready to be withdrawn.
+    referral_earnings_near: LookupMap<AccountId, NearToken>,
+    /// Fungible tokens earned by referrers, ready to be withdrawn.
+    referral_earnings_ft: LookupMap<(AccountId, FtId), U128>,
+    /// Large request and response payloads, by uploader and sha256.
+    blobs: LookupMap<(AccountId, CryptoHash), Blob>,
+    /// Requests made with an idempotency key, by consumer and key.
+    idempotent_requests: LookupMap<(ConsumerId, String), IdempotentRequest>,
+    /// Latencies of recent successful requests, by producer.
+    latency_windows: LookupMap<ProducerId, LatencyWindow>,
+    /// Keys of `idempotent_requests` in the order they were added.
+    idempotency_key_queue: IdempotencyKeyQueue,
 }
 
 #[cfg(feature = "contract")]
@@ -48,6 +100,13 @@ impl Default for Oracle {
             producers: LookupMap::new(StorageKey::Producers),
             consumers: LookupMap::new(StorageKey::Consumers),
             next_request_id: 0.into(),
+            transferred_producers: LookupMap::new(StorageKey::TransferredProducers),
+            referral_earnings_near: LookupMap::new(StorageKey::ReferralEarningsNear),
+            referral_earnings_ft: LookupMap::new(StorageKey::ReferralEarningsFt),
+            blobs: LookupMap::new(StorageKey::Blobs),
+            idempotent_requests: LookupMap::new(StorageKey::IdempotentRequests),
+            latency_windows: LookupMap::new(StorageKey::LatencyWindows),
+            idempotency_key_queue: IdempotencyKeyQueue::new(StorageKey::IdempotencyKeyQueue),
         }
     }
 }
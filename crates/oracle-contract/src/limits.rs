@@ -0,0 +1,149 @@
+use near_sdk::{env, near, BlockHeight};
+
+use crate::{consumer::ConsumerId, producer::ProducerId};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// Maximum number of requests a consumer can make to a producer in
+/// a window of `period_blocks` blocks.
+#[derive(Clone, PartialEq, Debug)]
+#[near(serializers=[borsh, json])]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub period_blocks: BlockHeight,
+}
+
+/// Number of requests a consumer has made in the current rate limit
+/// window.
+#[near(serializers=[borsh])]
+pub struct RateLimitUsage {
+    pub window_start_block: BlockHeight,
+    pub requests: u32,
+}
+
+impl RateLimitUsage {
+    fn requests_in_window(&self, rate_limit: &RateLimit, block_height: BlockHeight) -> u32 {
+        let window_end_block = self
+            .window_start_block
+            .saturating_add(rate_limit.period_blocks);
+        if block_height < window_end_block {
+            self.requests
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Sets the maximum number of requests that can be pending at the
+    /// same time, and the per-consumer rate limit. `None` removes the
+    /// limit. Requests over the limit are rejected before the fee is
+    /// charged.
+    pub fn set_request_limits(
+        &mut self,
+        max_pending_requests: Option<u64>,
+        rate_limit: Option<RateLimit>,
+    ) {
+        if let Some(rate_limit) = rate_limit.as_ref() {
+            near_sdk::require!(
+                rate_limit.period_blocks > 0,
+                "Rate limit period can't be 0 blocks"
+            );
+        }
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.max_pending_requests = max_pending_requests;
+        producer.rate_limit = rate_limit;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    pub fn get_pending_requests_count(&self, producer_id: ProducerId) -> u64 {
+        self.producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist")
+            .pending_requests_count
+    }
+
+    /// Returns the number of requests that can be made to the producer
+    /// before it reaches `max_pending_requests`, or `None` if it's
+    /// unlimited.
+    pub fn get_pending_capacity_left(&self, producer_id: ProducerId) -> Option<u64> {
+        let producer = self
+            .producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist");
+        producer
+            .max_pending_requests
+            .map(|max| max.saturating_sub(producer.pending_requests_count))
+    }
+
+    /// Returns the number of requests the consumer can make to the
+    /// producer in the current rate limit window, or `None` if it's
+    /// unlimited.
+    pub fn get_rate_limit_left(
+        &self,
+        producer_id: ProducerId,
+        consumer_id: ConsumerId,
+    ) -> Option<u32> {
+        let producer = self
+            .producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist");
+        let rate_limit = producer.rate_limit.as_ref()?;
+        let used = producer
+            .rate_limit_usage
+            .get(&consumer_id)
+            .map(|usage| usage.requests_in_window(rate_limit, env::block_height()))
+            .unwrap_or(0);
+        Some(rate_limit.max_requests.saturating_sub(used))
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Panics if the producer can't accept a new request from the
+    /// consumer, otherwise counts the request towards the limits.
+    pub fn enforce_request_limits(&mut self, consumer_id: &ConsumerId, producer_id: &ProducerId) {
+        let producer = self
+            .producers
+            .get_mut(producer_id)
+            .expect("Producer doesn't exist");
+        if let Some(max_pending_requests) = producer.max_pending_requests {
+            if producer.pending_requests_count >= max_pending_requests {
+                env::panic_str("Producer has too many pending requests, try again later")
+            }
+        }
+        producer.pending_requests_count += 1;
+
+        let Some(rate_limit) = producer.rate_limit.as_ref() else {
+            return;
+        };
+        let block_height = env::block_height();
+        if let Some(usage) = producer.rate_limit_usage.get_mut(consumer_id) {
+            if usage.requests_in_window(rate_limit, block_height) == 0 {
+                usage.window_start_block = block_height;
+                usage.requests = 0;
+            }
+            if usage.requests >= rate_limit.max_requests {
+                env::panic_str("Consumer exceeded the rate limit of this producer")
+            }
+            usage.requests += 1;
+        } else {
+            if rate_limit.max_requests == 0 {
+                env::panic_str("Consumer exceeded the rate limit of this producer")
+            }
+            producer.rate_limit_usage.insert(
+                consumer_id.clone(),
+                RateLimitUsage {
+                    window_start_block: block_height,
+                    requests: 1,
+                },
+            );
+        }
+    }
+}
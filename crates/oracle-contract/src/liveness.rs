@@ -0,0 +1,87 @@
+use near_sdk::{env, near, serde::Serialize, AccountId, BlockHeight, NearSchema};
+
+use crate::producer::{Producer, ProducerId};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+const MAX_OPERATORS: usize = 10;
+/// Threshold used for the liveness status of producers that send
+/// heartbeats but didn't set `liveness_threshold_blocks`. Same as the
+/// number of blocks after which a request times out.
+pub const DEFAULT_LIVENESS_THRESHOLD_BLOCKS: BlockHeight = 200;
+
+/// Producer details with the liveness status, returned by
+/// `get_producer_details`.
+#[derive(Serialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProducerDetails<'a> {
+    #[serde(flatten)]
+    pub producer: &'a Producer,
+    /// `None` if the producer doesn't send heartbeats.
+    pub is_online: Option<bool>,
+}
+
+impl Producer {
+    pub fn is_online(&self) -> Option<bool> {
+        if self.last_heartbeat_block.is_none() && self.liveness_threshold_blocks.is_none() {
+            return None;
+        }
+        let threshold = self
+            .liveness_threshold_blocks
+            .unwrap_or(DEFAULT_LIVENESS_THRESHOLD_BLOCKS);
+        Some(
+            self.last_heartbeat_block
+                .is_some_and(|last_heartbeat_block| {
+                    env::block_height().saturating_sub(last_heartbeat_block) <= threshold
+                }),
+        )
+    }
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Marks the producer as online. Can be called by the producer or
+    /// one of its operators, in which case `producer_id` is required.
+    pub fn heartbeat(&mut self, producer_id: Option<ProducerId>) {
+        let caller = env::predecessor_account_id();
+        let producer_id = producer_id.unwrap_or_else(|| caller.clone());
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer doesn't exist");
+        near_sdk::require!(
+            caller == producer_id || producer.operators.contains(&caller),
+            "Only the producer or its operators can send heartbeats"
+        );
+        producer.last_heartbeat_block = Some(env::block_height());
+    }
+
+    /// Sets accounts that can send heartbeats on behalf of the caller's
+    /// producer, for example hot keys of the nodes.
+    pub fn set_operators(&mut self, operators: Vec<AccountId>) {
+        near_sdk::require!(
+            operators.len() <= MAX_OPERATORS,
+            format!("Can't have more than {MAX_OPERATORS} operators")
+        );
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.operators = operators;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    /// If set, requests are rejected without charging the fee when the
+    /// last heartbeat is older than `liveness_threshold_blocks`.
+    pub fn set_liveness_threshold(&mut self, liveness_threshold_blocks: Option<BlockHeight>) {
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.liveness_threshold_blocks = liveness_threshold_blocks;
+
+        Oracle::emit_producer_updated(producer);
+    }
+}
@@ -0,0 +1,209 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize},
+    env,
+    json_types::U128,
+    log, near,
+    store::LookupMap,
+    AccountId, CryptoHash, IntoStorageKey, NearToken,
+};
+
+use crate::{
+    balance::FtId,
+    consumer::{Consumer, ConsumerId, RequestId},
+    fees::ProducerFee,
+    producer::{Producer, ProducerId},
+    StorageKey,
+};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// State of the first version of the contract.
+#[near(serializers=[borsh])]
+pub struct OracleV0 {
+    pub producers: LookupMap<ProducerId, ProducerV0>,
+    pub consumers: LookupMap<ConsumerId, ConsumerV0>,
+    pub next_request_id: RequestId,
+}
+
+#[near(serializers=[borsh])]
+pub struct ProducerV0 {
+    pub account_id: ProducerId,
+    pub requests_succeded: u64,
+    pub requests_timed_out: u64,
+    pub requests_pending: LookupMap<RequestId, PendingRequestV0>,
+    pub fee: ProducerFee,
+    pub send_callback: bool,
+    pub name: String,
+    pub description: String,
+    pub example_input: Option<String>,
+}
+
+#[near(serializers=[borsh])]
+pub struct PendingRequestV0 {
+    pub resumption_token: CryptoHash,
+}
+
+#[near(serializers=[borsh])]
+pub struct ConsumerV0 {
+    pub account_id: ConsumerId,
+    pub near_balance: NearToken,
+    pub near_balance_producer: LookupMap<ProducerId, NearToken>,
+    pub ft_balances: LookupMap<FtId, U128>,
+    pub ft_balances_producer: LookupMap<(ProducerId, FtId), U128>,
+    pub requests_succeeded: u64,
+    pub requests_timed_out: u64,
+}
+
+/// Balances of a consumer in the `OracleV0` layout. They are stored
+/// in maps that can't be iterated, so their keys have to be passed
+/// to `migrate_consumers`, for example from the `deposit_near` and
+/// `ft_on_transfer` logs.
+#[derive(Clone, Debug)]
+#[near(serializers=[json])]
+pub struct ConsumerV0Keys {
+    pub account_id: ConsumerId,
+    #[serde(default)]
+    pub producer_ids: Vec<ProducerId>,
+    #[serde(default)]
+    pub ft_ids: Vec<FtId>,
+    #[serde(default)]
+    pub producer_ft_ids: Vec<(ProducerId, FtId)>,
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Upgrades the state from `OracleV0`. Producers and consumers
+    /// keep their old layout until they are passed to
+    /// `migrate_producers` and `migrate_consumers`, and can't be used
+    /// before that. Requests that are in flight during the upgrade
+    /// can't be responded to, so the contract should be upgraded when
+    /// there are none.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old_state: OracleV0 = env::state_read().expect("No state to migrate");
+        Self {
+            next_request_id: old_state.next_request_id,
+            ..Self::default()
+        }
+    }
+
+    /// Converts producers from the `OracleV0` layout. Producers that
+    /// were already converted are skipped.
+    #[private]
+    pub fn migrate_producers(&mut self, account_ids: Vec<ProducerId>) {
+        for account_id in account_ids {
+            let Some(old_producer) =
+                read_v0::<ProducerV0>(StorageKey::Producers, &account_id, "Producer")
+            else {
+                continue;
+            };
+            let mut producer = Producer::new(account_id.clone());
+            producer.requests_succeded = old_producer.requests_succeded;
+            producer.requests_timed_out = old_producer.requests_timed_out;
+            producer.fee = old_producer.fee;
+            producer.send_callback = old_producer.send_callback;
+            producer.name = old_producer.name;
+            producer.description = old_producer.description;
+            producer.example_input = old_producer.example_input;
+            // `get` or `insert` would try to read the old layout
+            self.producers.set(account_id, Some(producer));
+        }
+    }
+
+    /// Converts consumers from the `OracleV0` layout, moving the
+    /// balances with the given keys. Consumers that were already
+    /// converted keep their state, but balances with the given keys
+    /// that are still in the old layout are moved, so keys that were
+    /// missed can be passed in another call.
+    #[private]
+    pub fn migrate_consumers(&mut self, consumers: Vec<ConsumerV0Keys>) {
+        for keys in consumers {
+            let account_id = keys.account_id;
+            if let Some(old_consumer) =
+                read_v0::<ConsumerV0>(StorageKey::Consumers, &account_id, "Consumer")
+            {
+                let mut consumer = Consumer::new(account_id.clone());
+                consumer.near_balance = old_consumer.near_balance;
+                consumer.requests_succeeded = old_consumer.requests_succeeded;
+                consumer.requests_timed_out = old_consumer.requests_timed_out;
+                // `get` or `insert` would try to read the old layout
+                self.consumers.set(account_id.clone(), Some(consumer));
+            }
+            let consumer = self.consumers.get_mut(&account_id).unwrap();
+            // The old maps have the same prefixes as the new ones, but
+            // `IterableMap` keeps its entries under `prefix + 'v'` and
+            // `prefix + 'm'`, which borsh-encoded account IDs can't
+            // start with, so they don't overlap.
+            let mut old_near_balance_producer =
+                LookupMap::<ProducerId, NearToken>::new(StorageKey::ConsumerNearBalanceProducer {
+                    consumer: account_id.clone(),
+                });
+            let mut old_ft_balances =
+                LookupMap::<FtId, U128>::new(StorageKey::ConsumerFtBalances {
+                    consumer: account_id.clone(),
+                });
+            let mut old_ft_balances_producer = LookupMap::<(ProducerId, FtId), U128>::new(
+                StorageKey::ConsumerFtBalancesProducers {
+                    consumer: account_id.clone(),
+                },
+            );
+            // The consumer could have deposited more since it was
+            // converted, so the old balances are added to the new ones
+            for producer_id in keys.producer_ids {
+                if let Some(balance) = old_near_balance_producer.remove(&producer_id) {
+                    let new_balance = consumer
+                        .near_balance_producer
+                        .get(&producer_id)
+                        .cloned()
+                        .unwrap_or(NearToken::from_near(0))
+                        .checked_add(balance)
+                        .expect("Overflow");
+                    consumer
+                        .near_balance_producer
+                        .insert(producer_id, new_balance);
+                }
+            }
+            for ft_id in keys.ft_ids {
+                if let Some(balance) = old_ft_balances.remove(&ft_id) {
+                    let new_balance = consumer.ft_balances.get(&ft_id).map_or(0, |b| b.0);
+                    consumer.ft_balances.insert(
+                        ft_id,
+                        U128(new_balance.checked_add(balance.0).expect("Overflow")),
+                    );
+                }
+            }
+            for key in keys.producer_ft_ids {
+                if let Some(balance) = old_ft_balances_producer.remove(&key) {
+                    let new_balance = consumer.ft_balances_producer.get(&key).map_or(0, |b| b.0);
+                    consumer.ft_balances_producer.insert(
+                        key,
+                        U128(new_balance.checked_add(balance.0).expect("Overflow")),
+                    );
+                }
+            }
+            old_near_balance_producer.flush();
+            old_ft_balances.flush();
+            old_ft_balances_producer.flush();
+        }
+    }
+}
+
+/// Reads an entry of a `LookupMap` in the old layout. Returns `None`
+/// if it was already converted, and panics if it doesn't exist.
+fn read_v0<T: BorshDeserialize>(
+    prefix: StorageKey,
+    account_id: &AccountId,
+    name: &str,
+) -> Option<T> {
+    let mut key = prefix.into_storage_key();
+    key.extend(borsh::to_vec(account_id).expect("Can't serialize account ID"));
+    let data = env::storage_read(&key)
+        .unwrap_or_else(|| env::panic_str(&format!("{name} {account_id} doesn't exist")));
+    let old = T::try_from_slice(&data).ok();
+    if old.is_none() {
+        log!("{name} {account_id} is already migrated");
+    }
+    old
+}
@@ -0,0 +1,211 @@
+use near_sdk::{env, near, serde_json, Gas, NearToken, PromiseError, PromiseIndex};
+
+use crate::{
+    consumer::{ConsumerId, RequestOptions},
+    gas::{extension_gas, REQUEST_GAS},
+    producer::{ProducerId, Response},
+};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+/// Placeholder in `PipelineStep::template` that is replaced with the
+/// previous step's `response_data`.
+pub const PIPELINE_PLACEHOLDER: &str = "{{response_data}}";
+const MAX_PIPELINE_STEPS: usize = 5;
+/// Gas for each of the pipeline's own calls around a step, without
+/// the request it makes.
+const PIPELINE_STEP_GAS: Gas = Gas::from_tgas(5);
+
+#[derive(Clone, Debug)]
+#[near(serializers=[json])]
+pub struct PipelineStep {
+    pub producer_id: ProducerId,
+    /// `request_data` of this step. `{{response_data}}` is replaced
+    /// with the `response_data` of the previous step, escaped as the
+    /// contents of a JSON string: quotes, backslashes and control
+    /// characters are backslash-escaped, so a JSON template should
+    /// put the placeholder inside quotes, like
+    /// `{"text": "{{response_data}}"}`.
+    pub template: String,
+}
+
+/// Result of `request_pipeline`.
+#[near(serializers=[json])]
+pub struct PipelineResult {
+    /// Response of the last step that succeeded, or `None` if the
+    /// first step timed out.
+    pub response: Option<Response>,
+    /// Index of the step that timed out or couldn't be requested, for
+    /// example because the consumer ran out of balance. The steps
+    /// after it are not requested. `None` if all steps succeeded.
+    pub failed_step: Option<u32>,
+}
+
+/// Escapes `response_data` for `PipelineStep::template`.
+fn escape_response_data(response_data: &str) -> String {
+    let quoted = serde_json::to_string(response_data).expect("Can't serialize response data");
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Requests data from several producers in a row, passing each
+    /// response to the next step. Each step is charged like a separate
+    /// `request` from the caller's deposited balance and refunded if it
+    /// times out. If a step fails, the following steps are not
+    /// requested, and the response of the last step that succeeded is
+    /// returned, see `PipelineResult`.
+    pub fn request_pipeline(&mut self, steps: Vec<PipelineStep>) {
+        near_sdk::require!(!steps.is_empty(), "Pipeline must have at least one step");
+        near_sdk::require!(
+            steps.len() <= MAX_PIPELINE_STEPS,
+            format!("Can't have more than {MAX_PIPELINE_STEPS} steps")
+        );
+        let consumer_id = env::predecessor_account_id();
+        let request_data = steps[0].template.clone();
+        let promise_idx = self.request_pipeline_step(consumer_id, steps, 0, request_data, None);
+        env::promise_return(promise_idx);
+    }
+
+    /// Requests a step after the first one. Runs in its own receipt,
+    /// so that if the request can't be made, `on_pipeline_step_requested`
+    /// still returns the previous step's response.
+    #[private]
+    pub fn pipeline_step(
+        &mut self,
+        consumer_id: ConsumerId,
+        steps: Vec<PipelineStep>,
+        step: u32,
+        request_data: String,
+        previous: Response,
+    ) {
+        let promise_idx = self.request_pipeline_step(
+            consumer_id,
+            steps,
+            step as usize,
+            request_data,
+            Some(previous),
+        );
+        env::promise_return(promise_idx);
+    }
+
+    #[private]
+    pub fn on_pipeline_step(
+        &mut self,
+        consumer_id: ConsumerId,
+        steps: Vec<PipelineStep>,
+        step: u32,
+        previous: Option<Response>,
+        #[callback_result] response: Result<Option<Response>, PromiseError>,
+    ) {
+        let next_step = step as usize + 1;
+        let result = match response.ok().flatten() {
+            Some(response) if next_step < steps.len() => {
+                let request_data = steps[next_step].template.replace(
+                    PIPELINE_PLACEHOLDER,
+                    &escape_response_data(&response.response_data),
+                );
+                let gas = self.pipeline_steps_gas(&steps[next_step..]);
+                Self::ext(env::current_account_id())
+                    .with_static_gas(gas.saturating_sub(PIPELINE_STEP_GAS))
+                    .pipeline_step(
+                        consumer_id,
+                        steps,
+                        next_step as u32,
+                        request_data,
+                        response.clone(),
+                    )
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(PIPELINE_STEP_GAS)
+                            .on_pipeline_step_requested(next_step as u32, response),
+                    )
+                    .as_return();
+                return;
+            }
+            Some(response) => PipelineResult {
+                response: Some(response),
+                failed_step: None,
+            },
+            None => PipelineResult {
+                response: previous,
+                failed_step: Some(step),
+            },
+        };
+        env::value_return(
+            &serde_json::to_vec(&result).expect("Can't serialize on_pipeline_step result"),
+        );
+    }
+
+    /// Passes on the result of the rest of the pipeline, or returns
+    /// `previous` if `step` couldn't be requested.
+    #[private]
+    pub fn on_pipeline_step_requested(
+        &self,
+        step: u32,
+        previous: Response,
+        #[callback_result] result: Result<PipelineResult, PromiseError>,
+    ) -> PipelineResult {
+        result.unwrap_or(PipelineResult {
+            response: Some(previous),
+            failed_step: Some(step),
+        })
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Makes the request of `step` and schedules `on_pipeline_step`
+    /// after it, with enough gas to request the remaining steps.
+    fn request_pipeline_step(
+        &mut self,
+        consumer_id: ConsumerId,
+        steps: Vec<PipelineStep>,
+        step: usize,
+        request_data: String,
+        previous: Option<Response>,
+    ) -> PromiseIndex {
+        let request_idx = self.create_request(
+            consumer_id.clone(),
+            steps[step].producer_id.clone(),
+            request_data,
+            RequestOptions::default(),
+        );
+        let gas = PIPELINE_STEP_GAS.saturating_add(self.pipeline_steps_gas(&steps[step + 1..]));
+        env::promise_then(
+            request_idx,
+            env::current_account_id(),
+            "on_pipeline_step",
+            &serde_json::to_vec(&serde_json::json!({
+                "consumer_id": consumer_id,
+                "steps": steps,
+                "step": step as u32,
+                "previous": previous,
+            }))
+            .expect("Can't serialize on_pipeline_step args"),
+            NearToken::from_yoctonear(0),
+            gas,
+        )
+    }
+
+    /// Gas that `on_pipeline_step` needs to request `steps` after the
+    /// one it's called for: `pipeline_step` with the request,
+    /// `on_pipeline_step` and `on_pipeline_step_requested` for each.
+    /// Doesn't panic for producers that don't exist, since their
+    /// requests fail in `pipeline_step`.
+    fn pipeline_steps_gas(&self, steps: &[PipelineStep]) -> Gas {
+        steps.iter().fold(Gas::from_gas(0), |gas, step| {
+            let on_response_gas =
+                self.producers
+                    .get(&step.producer_id)
+                    .map_or(Gas::from_gas(0), |producer| {
+                        self.min_on_response_gas(&step.producer_id)
+                            .saturating_add(extension_gas(producer.max_extensions))
+                    });
+            gas.saturating_add(REQUEST_GAS)
+                .saturating_add(on_response_gas)
+                .saturating_add(PIPELINE_STEP_GAS.saturating_mul(3))
+        })
+    }
+}
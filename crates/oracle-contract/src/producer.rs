@@ -1,14 +1,26 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::Serialize;
 use near_sdk::store::LookupMap;
 use near_sdk::NearSchema;
 use near_sdk::{
-    env, ext_contract, json_types::U128, log, near, serde_json, AccountId, PromiseError,
+    env, ext_contract,
+    json_types::{Base58CryptoHash, Base64VecU8, U128, U64},
+    log, near, serde_json, AccountId, BlockHeight, NearToken, Promise, PromiseError,
 };
+use std::collections::BTreeMap;
 
-use crate::consumer::OracleEvent;
+use crate::consumer::{OracleEvent, ProducerTransferredEventV1};
 use crate::{
-    consumer::{ConsumerId, PendingRequest, RequestId},
-    fees::{PrepaidFee, ProducerFee},
+    balance::FtId,
+    consumer::{
+        yield_on_response, ConsumerId, PendingRequest, ProgressEventV1, RequestAcknowledgedEventV1,
+        RequestContext, RequestId,
+    },
+    fees::{Beneficiary, PrepaidFee, ProducerFee, VolumeTier},
+    idempotency::RequestStatus,
+    limits::{RateLimit, RateLimitUsage},
+    liveness::ProducerDetails,
+    sponsorship::{SponsoredUsage, SponsorshipRules},
     StorageKey,
 };
 #[cfg(feature = "contract")]
@@ -16,10 +28,158 @@ use crate::{Oracle, OracleExt};
 
 pub type ProducerId = AccountId;
 
+const MAX_PROGRESS_DATA_LENGTH: usize = 1024;
+/// Maximum size of the data that a yielded execution can be resumed
+/// with, a NEAR protocol-level parameter.
+const MAX_RESUME_PAYLOAD_LENGTH: usize = 1024;
+/// Maximum `max_extensions` of a producer. Gas for every extension is
+/// reserved when the request is made, see `EXTENSION_GAS`.
+const MAX_EXTENSIONS: u32 = 10;
+/// Number of blocks after which a yielded execution times out, a NEAR
+/// protocol-level parameter.
+const YIELD_TIMEOUT_BLOCKS: BlockHeight = 200;
+
+/// Response of a producer. Serialized as JSON, it can't be longer than
+/// 1024 bytes, the limit of the data that a yielded execution can be
+/// resumed with, so larger data should be uploaded as a blob.
+#[derive(Clone)]
 #[near(serializers=[json])]
 pub struct Response {
     pub response_data: String,
+    /// Binary response data, for example a signature or a
+    /// Borsh-encoded struct.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_bytes: Option<Base64VecU8>,
     pub refund_amount: Option<U128>,
+    /// Blob uploaded by the producer with `upload_blob` that holds
+    /// data too large for `response_data`. The consumer fetches it
+    /// with `get_blob(producer_id, response_blob)` and removes it
+    /// with `remove_blob`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_blob: Option<Base58CryptoHash>,
+}
+
+impl Response {
+    /// Creates a response with `value` Borsh-encoded in
+    /// `response_bytes`.
+    pub fn borsh<T: BorshSerialize>(value: &T, refund_amount: Option<U128>) -> Self {
+        Self {
+            response_data: String::new(),
+            response_bytes: Some(
+                borsh::to_vec(value)
+                    .expect("Failed to serialize response")
+                    .into(),
+            ),
+            refund_amount,
+            response_blob: None,
+        }
+    }
+
+    /// Decodes `response_bytes` as Borsh. Returns `None` if there are
+    /// no bytes or they don't match `T`.
+    pub fn parse_borsh<T: BorshDeserialize>(&self) -> Option<T> {
+        borsh::from_slice(&self.response_bytes.as_ref()?.0).ok()
+    }
+}
+
+/// Data that a yielded `on_response` callback is resumed with.
+#[near(serializers=[json])]
+pub enum YieldResumption {
+    Response(Response),
+    /// The producer needs more time, wait for another yield.
+    Extend,
+    /// The consumer's deadline has passed, treat the request as
+    /// timed out.
+    Expire,
+}
+
+/// How a request ended, see `Oracle::settle_request`.
+enum RequestOutcome {
+    Succeeded(Response),
+    /// The consumer's validator rejected the response.
+    Rejected(Response),
+    TimedOut,
+}
+
+/// Number of most recent successful requests that latency stats are
+/// calculated from.
+pub const LATENCY_WINDOW: usize = 100;
+
+/// Response latency of a producer over the last `LATENCY_WINDOW`
+/// successful requests.
+#[near(serializers=[borsh])]
+#[derive(Serialize, NearSchema, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LatencyStats {
+    pub average_latency_blocks: Option<u64>,
+    pub p95_latency_blocks: Option<u64>,
+    /// Block timestamp of the last successful response.
+    pub last_response_timestamp_nanos: Option<U64>,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, window: &mut LatencyWindow, latency_blocks: BlockHeight) {
+        window.push(latency_blocks);
+        self.average_latency_blocks = Some(window.average());
+        self.p95_latency_blocks = Some(window.p95());
+        self.last_response_timestamp_nanos = Some(env::block_timestamp().into());
+    }
+}
+
+/// Latencies that `LatencyStats` are calculated from. Stored apart
+/// from `Producer`, so that it's only loaded when a request succeeds.
+#[near(serializers=[borsh])]
+#[derive(Default)]
+pub struct LatencyWindow {
+    /// Ring buffer of latencies, in blocks.
+    recent_latencies: Vec<BlockHeight>,
+    /// Position in `recent_latencies` that will be overwritten next
+    /// once the buffer is full.
+    next_index: u32,
+    /// Number of times each latency occurs in `recent_latencies`.
+    histogram: BTreeMap<BlockHeight, u32>,
+    /// Sum of `recent_latencies`.
+    total_latency_blocks: u64,
+}
+
+impl LatencyWindow {
+    fn push(&mut self, latency_blocks: BlockHeight) {
+        if self.recent_latencies.len() < LATENCY_WINDOW {
+            self.recent_latencies.push(latency_blocks);
+        } else {
+            let oldest = std::mem::replace(
+                &mut self.recent_latencies[self.next_index as usize],
+                latency_blocks,
+            );
+            self.total_latency_blocks -= oldest;
+            if let Some(count) = self.histogram.get_mut(&oldest) {
+                *count -= 1;
+                if *count == 0 {
+                    self.histogram.remove(&oldest);
+                }
+            }
+        }
+        self.next_index = (self.next_index + 1) % LATENCY_WINDOW as u32;
+        self.total_latency_blocks += latency_blocks;
+        *self.histogram.entry(latency_blocks).or_default() += 1;
+    }
+
+    fn average(&self) -> u64 {
+        self.total_latency_blocks / self.recent_latencies.len() as u64
+    }
+
+    /// Nearest-rank percentile
+    fn p95(&self) -> u64 {
+        let rank = (self.recent_latencies.len() * 95).div_ceil(100);
+        let mut seen = 0;
+        for (&latency_blocks, &count) in &self.histogram {
+            seen += count as usize;
+            if seen >= rank {
+                return latency_blocks;
+            }
+        }
+        unreachable!()
+    }
 }
 
 /// A producer is an account that provides data to consumers.
@@ -41,7 +201,7 @@ pub struct Producer {
     /// Producers meant for public use may want to charge a fee.
     pub fee: ProducerFee,
     /// If true, the contract will receive `on_request(request_id,
-    /// request_data, prepaid_fee)` call
+    /// request_data, prepaid_fee, end_user)` call
     pub send_callback: bool,
     /// Name of the producer.
     pub name: String,
@@ -50,19 +210,78 @@ pub struct Producer {
     /// Example input that can be used in usage examples on the
     /// oracle dashboard.
     pub example_input: Option<String>,
+    /// Rules that decide which consumers get their requests paid
+    /// from the producer's sponsorship pool.
+    pub sponsorship_rules: SponsorshipRules,
+    /// NEAR in the sponsorship pool.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub sponsorship_near_balance: NearToken,
+    /// Fungible token balances of the sponsorship pool.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub sponsorship_ft_balances: LookupMap<FtId, U128>,
+    /// Number of sponsored requests each consumer has made in the
+    /// current epoch.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub sponsored_usage: LookupMap<ConsumerId, SponsoredUsage>,
+    /// Discounts for consumers that make many requests to this
+    /// producer, sorted by `min_requests`.
+    pub volume_tiers: Vec<VolumeTier>,
+    /// Account that can accept the ownership transfer of this
+    /// producer.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub pending_owner: Option<AccountId>,
+    /// Accounts that the producer's earnings are split between. If
+    /// empty, everything goes to the producer.
+    pub beneficiaries: Vec<Beneficiary>,
+    /// Share of the fee, in basis points, that is paid to the
+    /// referrer of a request.
+    pub referral_fee_bps: u16,
+    /// Response latency of recent requests.
+    pub latency: LatencyStats,
+    /// Maximum number of requests that can be pending at the same
+    /// time. `None` means unlimited.
+    pub max_pending_requests: Option<u64>,
+    /// Maximum number of requests each consumer can make in a
+    /// window of blocks. `None` means unlimited.
+    pub rate_limit: Option<RateLimit>,
+    /// Number of requests that haven't received a response or
+    /// timed out yet.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub pending_requests_count: u64,
+    /// Number of requests each consumer has made in the current
+    /// rate limit window.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub rate_limit_usage: LookupMap<ConsumerId, RateLimitUsage>,
+    /// Accounts that can send heartbeats on behalf of the producer.
+    pub operators: Vec<AccountId>,
+    /// Block of the last `heartbeat` call.
+    pub last_heartbeat_block: Option<BlockHeight>,
+    /// If set, requests are rejected when the last heartbeat is
+    /// older than this number of blocks.
+    pub liveness_threshold_blocks: Option<BlockHeight>,
+    /// Number of times a request can be extended with `acknowledge`,
+    /// each extension gives the producer another ~200 blocks.
+    pub max_extensions: u32,
+    /// X25519 public key that consumers can encrypt requests to, see
+    /// `EncryptedEnvelope`.
+    pub encryption_public_key: Option<Base64VecU8>,
+    /// Number of responses rejected by consumers' validators.
+    pub requests_rejected: u64,
+    /// If true, consumers can set a `validator` on their requests,
+    /// and the producer isn't paid for responses that it rejects.
+    pub accepts_validators: bool,
 }
 
-#[ext_contract(ext_producer)]
-pub trait ProducerContract {
-    fn on_request(&mut self, request_id: RequestId, request_data: String, prepaid_fee: PrepaidFee);
-}
-
-#[cfg(feature = "contract")]
-#[near]
-impl Oracle {
-    pub fn add_producer(&mut self) {
-        let account_id = env::predecessor_account_id();
-        let producer = Producer {
+impl Producer {
+    /// New producer with the default settings.
+    pub fn new(account_id: ProducerId) -> Self {
+        Producer {
             account_id: account_id.clone(),
             requests_succeded: 0,
             requests_timed_out: 0,
@@ -74,26 +293,109 @@ impl Oracle {
             name: "Unnamed".to_string(),
             description: "No description".to_string(),
             example_input: None,
-        };
-        OracleEvent::ProducerCreated(Producer {
-            account_id: producer.account_id.clone(),
-            requests_succeded: producer.requests_succeded,
-            requests_timed_out: producer.requests_timed_out,
+            sponsorship_rules: SponsorshipRules::default(),
+            sponsorship_near_balance: NearToken::from_near(0),
+            sponsorship_ft_balances: LookupMap::new(StorageKey::ProducerSponsorshipFtBalances {
+                producer: account_id.clone(),
+            }),
+            sponsored_usage: LookupMap::new(StorageKey::ProducerSponsoredUsage {
+                producer: account_id.clone(),
+            }),
+            volume_tiers: Vec::new(),
+            pending_owner: None,
+            beneficiaries: Vec::new(),
+            referral_fee_bps: 0,
+            latency: LatencyStats::default(),
+            max_pending_requests: None,
+            rate_limit: None,
+            pending_requests_count: 0,
+            rate_limit_usage: LookupMap::new(StorageKey::ProducerRateLimitUsage {
+                producer: account_id.clone(),
+            }),
+            operators: Vec::new(),
+            last_heartbeat_block: None,
+            liveness_threshold_blocks: None,
+            max_extensions: 0,
+            encryption_public_key: None,
+            requests_rejected: 0,
+            accepts_validators: false,
+        }
+    }
+
+    /// Copy of the producer for `ProducerCreated` and
+    /// `ProducerUpdated` events. Collections are not serialized, so
+    /// they're replaced with empty placeholders.
+    pub fn event_copy(&self) -> Producer {
+        Producer {
+            account_id: self.account_id.clone(),
+            requests_succeded: self.requests_succeded,
+            requests_timed_out: self.requests_timed_out,
             requests_pending: LookupMap::new(b"dontcare".as_slice()),
-            fee: producer.fee.clone(),
-            send_callback: producer.send_callback,
-            name: producer.name.clone(),
-            description: producer.description.clone(),
-            example_input: producer.example_input.clone(),
-        })
-        .emit();
+            fee: self.fee.clone(),
+            send_callback: self.send_callback,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            example_input: self.example_input.clone(),
+            sponsorship_rules: self.sponsorship_rules.clone(),
+            sponsorship_near_balance: self.sponsorship_near_balance,
+            sponsorship_ft_balances: LookupMap::new(b"dontcare".as_slice()),
+            sponsored_usage: LookupMap::new(b"dontcare".as_slice()),
+            volume_tiers: self.volume_tiers.clone(),
+            pending_owner: self.pending_owner.clone(),
+            beneficiaries: self.beneficiaries.clone(),
+            referral_fee_bps: self.referral_fee_bps,
+            latency: self.latency.clone(),
+            max_pending_requests: self.max_pending_requests,
+            rate_limit: self.rate_limit.clone(),
+            pending_requests_count: self.pending_requests_count,
+            rate_limit_usage: LookupMap::new(b"dontcare".as_slice()),
+            operators: self.operators.clone(),
+            last_heartbeat_block: self.last_heartbeat_block,
+            liveness_threshold_blocks: self.liveness_threshold_blocks,
+            max_extensions: self.max_extensions,
+            encryption_public_key: self.encryption_public_key.clone(),
+            requests_rejected: self.requests_rejected,
+            accepts_validators: self.accepts_validators,
+        }
+    }
+}
+
+#[ext_contract(ext_producer)]
+pub trait ProducerContract {
+    fn on_request(
+        &mut self,
+        request_id: RequestId,
+        request_data: String,
+        prepaid_fee: PrepaidFee,
+        end_user: Option<String>,
+        request_bytes: Option<Base64VecU8>,
+        request_blob: Option<Base58CryptoHash>,
+    );
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    pub fn add_producer(&mut self) {
+        let account_id = env::predecessor_account_id();
+        near_sdk::require!(
+            !self.transferred_producers.contains_key(&account_id),
+            "This producer was transferred to another account"
+        );
+        let producer = Producer::new(account_id.clone());
+        OracleEvent::ProducerCreated(producer.event_copy()).emit();
         self.producers.insert(account_id, producer);
     }
 
-    pub fn get_producer_details(&self, account_id: ProducerId) -> &Producer {
-        self.producers
+    pub fn get_producer_details(&self, account_id: ProducerId) -> ProducerDetails<'_> {
+        let producer = self
+            .producers
             .get(&account_id)
-            .expect("Producer doesn't exist")
+            .expect("Producer doesn't exist");
+        ProducerDetails {
+            producer,
+            is_online: producer.is_online(),
+        }
     }
 
     pub fn edit_producer_details(
@@ -110,96 +412,410 @@ impl Oracle {
         producer.description = description;
         producer.example_input = example_input;
 
-        OracleEvent::ProducerUpdated(Producer {
-            account_id: producer.account_id.clone(),
-            requests_succeded: producer.requests_succeded,
-            requests_timed_out: producer.requests_timed_out,
-            requests_pending: LookupMap::new(b"dontcare".as_slice()),
-            fee: producer.fee.clone(),
-            send_callback: producer.send_callback,
-            name: producer.name.clone(),
-            description: producer.description.clone(),
-            example_input: producer.example_input.clone(),
-        })
-        .emit();
+        Oracle::emit_producer_updated(producer);
     }
 
     pub fn is_producer(&self, account_id: ProducerId) -> bool {
         self.producers.contains_key(&account_id)
     }
 
-    pub fn set_send_callback(&mut self, send_callback: bool) {
+    /// Returns the latest progress reported for a pending request.
+    pub fn get_request_progress(
+        &self,
+        producer_id: ProducerId,
+        request_id: RequestId,
+    ) -> Option<&String> {
+        self.producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist")
+            .requests_pending
+            .get(&request_id)
+            .and_then(|pending_request| pending_request.progress.as_ref())
+    }
+
+    /// Starts transferring the caller's producer to `new_account_id`,
+    /// which has to call `accept_producer_transfer` to complete it.
+    /// Stats, fee, metadata and pending requests are moved to the new
+    /// account. Consumers' balances for this producer are not moved,
+    /// consumers can move them with `transfer_balance`.
+    pub fn propose_producer_transfer(&mut self, new_account_id: AccountId) {
+        near_sdk::require!(
+            !self.producers.contains_key(&new_account_id)
+                && !self.transferred_producers.contains_key(&new_account_id),
+            "New account can't be used as a producer"
+        );
         let producer = self
             .producers
             .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.pending_owner = Some(new_account_id);
+    }
+
+    pub fn cancel_producer_transfer(&mut self) {
+        self.producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered")
+            .pending_owner = None;
+    }
+
+    pub fn get_pending_producer_transfer(&self, producer_id: ProducerId) -> Option<AccountId> {
+        self.producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist")
+            .pending_owner
+            .clone()
+    }
+
+    pub fn accept_producer_transfer(&mut self, producer_id: ProducerId) {
+        let new_account_id = env::predecessor_account_id();
+        near_sdk::require!(
+            !self.producers.contains_key(&new_account_id)
+                && !self.transferred_producers.contains_key(&new_account_id),
+            "New account can't be used as a producer"
+        );
+        let mut producer = self
+            .producers
+            .remove(&producer_id)
             .expect("Producer doesn't exist");
-        producer.send_callback = send_callback;
+        near_sdk::require!(
+            producer.pending_owner.as_ref() == Some(&new_account_id),
+            "Transfer to this account was not proposed"
+        );
+        producer.account_id = new_account_id.clone();
+        producer.pending_owner = None;
+        self.transferred_producers
+            .insert(producer_id.clone(), new_account_id.clone());
+        if let Some(latency_window) = self.latency_windows.remove(&producer_id) {
+            self.latency_windows
+                .insert(new_account_id.clone(), latency_window);
+        }
 
-        OracleEvent::ProducerUpdated(Producer {
-            account_id: producer.account_id.clone(),
-            requests_succeded: producer.requests_succeded,
-            requests_timed_out: producer.requests_timed_out,
-            requests_pending: LookupMap::new(b"dontcare".as_slice()),
-            fee: producer.fee.clone(),
-            send_callback: producer.send_callback,
-            name: producer.name.clone(),
-            description: producer.description.clone(),
-            example_input: producer.example_input.clone(),
+        OracleEvent::ProducerTransferred(ProducerTransferredEventV1 {
+            old_producer_id: producer_id,
+            new_producer_id: new_account_id.clone(),
         })
         .emit();
+        Oracle::emit_producer_updated(&producer);
+        self.producers.insert(new_account_id, producer);
+    }
+
+    pub fn set_send_callback(&mut self, send_callback: bool) {
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer doesn't exist");
+        producer.send_callback = send_callback;
+
+        Oracle::emit_producer_updated(producer);
     }
 
+    /// Returns `Option<Response>` to the consumer once the producer
+    /// responds or the request times out, and sends it to the
+    /// consumer's `callback` if there is one. If the producer extended
+    /// the request, waits for another yield instead. If the consumer
+    /// set a `validator`, the response is settled in `on_validated`.
     #[private]
     pub fn on_response(
         &mut self,
-        producer_id: ProducerId,
-        request_id: RequestId,
-        consumer_id: ConsumerId,
-        fee: PrepaidFee,
-        #[callback_result] response: Result<Response, PromiseError>,
-    ) -> Option<Response> {
+        context: RequestContext,
+        #[callback_result] resumption: Result<YieldResumption, PromiseError>,
+    ) {
+        match resumption {
+            Ok(YieldResumption::Response(response)) => {
+                if let Some(validator) = context.validator.clone() {
+                    let settlement_gas = context.settlement_gas();
+                    Promise::new(validator.contract_id)
+                        .function_call(
+                            validator.method_name,
+                            serde_json::to_vec(&serde_json::json!({
+                                "request_id": context.request_id,
+                                "producer_id": context.producer_id,
+                                "response": response,
+                            }))
+                            .expect("Can't serialize validator args"),
+                            NearToken::from_yoctonear(0),
+                            validator.gas,
+                        )
+                        .then(
+                            Self::ext(env::current_account_id())
+                                .with_static_gas(settlement_gas)
+                                .on_validated(context, response),
+                        )
+                        .as_return();
+                } else {
+                    self.settle_request(context, RequestOutcome::Succeeded(response));
+                }
+            }
+            Ok(YieldResumption::Extend) => {
+                let mut context = context;
+                // `acknowledge` doesn't allow more extensions than there's
+                // gas reserved for, but if the next yield can't be paid
+                // for anyway, time out now instead of locking the fee
+                let Some(extensions_left) = context.extensions_left.checked_sub(1) else {
+                    self.settle_request(context, RequestOutcome::TimedOut);
+                    return;
+                };
+                context.extensions_left = extensions_left;
+                if env::prepaid_gas().saturating_sub(env::used_gas()) < context.yield_gas() {
+                    self.settle_request(context, RequestOutcome::TimedOut);
+                    return;
+                }
+                let (promise_idx, resumption_token) = yield_on_response(&context);
+                let producer_id = self.resolve_producer_id(context.producer_id);
+                self.producers
+                    .get_mut(&producer_id)
+                    .expect("Producer doesn't exist")
+                    .requests_pending
+                    .get_mut(&context.request_id)
+                    .expect("Request not found")
+                    .resumption_token = resumption_token;
+                env::promise_return(promise_idx);
+            }
+            // Expired requests are handled the same way as timeouts
+            Ok(YieldResumption::Expire) | Err(_) => {
+                self.settle_request(context, RequestOutcome::TimedOut);
+            }
+        }
+    }
+
+    /// Settles a response after the consumer's validator checked it.
+    /// Only an explicit `false` rejects the response, a validator that
+    /// fails doesn't count against the producer.
+    #[private]
+    pub fn on_validated(
+        &mut self,
+        context: RequestContext,
+        response: Response,
+        #[callback_result] valid: Result<bool, PromiseError>,
+    ) {
+        let outcome = if matches!(valid, Ok(false)) {
+            RequestOutcome::Rejected(response)
+        } else {
+            RequestOutcome::Succeeded(response)
+        };
+        self.settle_request(context, outcome);
+    }
+
+    /// Times out a request whose `deadline_blocks` has passed, so that
+    /// the consumer gets a full refund without waiting for the yield
+    /// timeout. Can be called by anyone.
+    pub fn expire(&mut self, producer_id: ProducerId, request_id: RequestId) {
+        let producer_id = self.resolve_producer_id(producer_id);
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer doesn't exist");
+        let pending_request = producer
+            .requests_pending
+            .get(&request_id)
+            .unwrap_or_else(|| env::panic_str("Request not found or already responded to"));
+        let deadline_block = pending_request
+            .deadline_block
+            .unwrap_or_else(|| env::panic_str("Request has no deadline"));
+        near_sdk::require!(
+            env::block_height() > deadline_block,
+            "Request deadline hasn't passed yet"
+        );
+        let pending_request = producer.requests_pending.remove(&request_id).unwrap();
+        if !env::promise_yield_resume(
+            &pending_request.resumption_token,
+            &serde_json::to_vec(&YieldResumption::Expire)
+                .expect("Can't serialize on_response args"),
+        ) {
+            env::panic_str("Resumption token not found")
+        }
+    }
+
+    /// Lets consumers set a `validator` on requests to this producer.
+    /// A consumer whose validator rejects a response gets a full
+    /// refund, so only producers that are fine with that should opt
+    /// in. Only applies to requests made after this call.
+    pub fn set_accepts_validators(&mut self, accepts_validators: bool) {
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer doesn't exist");
+        producer.accepts_validators = accepts_validators;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    /// Sets how many times a request can be extended. Only applies to
+    /// requests made after this call.
+    pub fn set_max_extensions(&mut self, max_extensions: u32) {
+        near_sdk::require!(
+            max_extensions <= MAX_EXTENSIONS,
+            format!("Can't have more than {MAX_EXTENSIONS} extensions")
+        );
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer doesn't exist");
+        producer.max_extensions = max_extensions;
+
+        Oracle::emit_producer_updated(producer);
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Emits `ProducerUpdated` with the current state of the producer.
+    /// Takes the producer instead of `&self`, so it can be called while
+    /// the producer is borrowed mutably from `self.producers`.
+    pub fn emit_producer_updated(producer: &Producer) {
+        OracleEvent::ProducerUpdated(producer.event_copy()).emit();
+    }
+
+    /// Pays the producer or refunds the consumer, and returns
+    /// `Option<Response>` to the consumer.
+    fn settle_request(&mut self, context: RequestContext, outcome: RequestOutcome) {
+        let RequestContext {
+            producer_id,
+            request_id,
+            consumer_id,
+            fee,
+            referrer,
+            created_at_block,
+            request_blob,
+            callback,
+            idempotency_key,
+            beneficiaries,
+            ..
+        } = context;
+        let rejected = matches!(outcome, RequestOutcome::Rejected(_));
+        let response = match outcome {
+            RequestOutcome::Succeeded(response) | RequestOutcome::Rejected(response) => {
+                Ok(response)
+            }
+            RequestOutcome::TimedOut => Err(PromiseError::Failed),
+        };
+        // The producer could have been transferred while the request was in flight
+        let producer_id = self.resolve_producer_id(producer_id);
+        if let Some(request_blob) = request_blob {
+            self.release_blob(&consumer_id, request_blob);
+        }
+        if let Some(response_blob) = response
+            .as_ref()
+            .ok()
+            .and_then(|response| response.response_blob)
+        {
+            if rejected {
+                self.release_blob(&producer_id, response_blob);
+            } else {
+                self.hand_over_blob(&producer_id, response_blob, &consumer_id);
+            }
+        }
         let producer = self
             .producers
             .get_mut(&producer_id)
             .expect("Producer doesn't exist");
+        producer.pending_requests_count = producer.pending_requests_count.saturating_sub(1);
         log!(
             "Response from {producer_id} for {request_id}: {response:?}, refund {refund:?}",
             request_id = request_id.0,
             response = response.as_ref().map(|r| &r.response_data),
             refund = response.as_ref().map(|r| &r.refund_amount),
         );
-        if let Ok(response) = response.as_ref() {
+        if rejected {
+            log!(
+                "Response for {request_id} was rejected by the validator",
+                request_id = request_id.0,
+            );
+            producer.requests_rejected += 1;
+            self.refund_fully(&consumer_id, &producer_id, &fee);
+        } else if let Ok(response) = response.as_ref() {
             producer.requests_succeded += 1;
+            let latency_window = self.latency_windows.entry(producer_id.clone()).or_default();
+            producer.latency.record(
+                latency_window,
+                env::block_height().saturating_sub(created_at_block),
+            );
             if let Some(refund_amount) = response.refund_amount {
                 self.refund_partially(&consumer_id, &producer_id, &fee, refund_amount);
             }
-            self.deposit_to_producer(producer_id.clone(), &fee, response.refund_amount);
+            let commission = if let Some(referrer) = referrer.as_ref() {
+                self.pay_referral_commission(
+                    referrer,
+                    &consumer_id,
+                    &producer_id,
+                    request_id,
+                    &fee,
+                    response.refund_amount,
+                )
+            } else {
+                U128(0)
+            };
+            self.deposit_to_producer(
+                producer_id.clone(),
+                &beneficiaries,
+                &fee,
+                response.refund_amount,
+                commission,
+            );
         } else {
             producer.requests_timed_out += 1;
+            producer.requests_pending.remove(&request_id);
             self.refund_fully(&consumer_id, &producer_id, &fee);
         }
+        let status = if rejected {
+            RequestStatus::Rejected
+        } else if response.is_ok() {
+            RequestStatus::Succeeded
+        } else {
+            RequestStatus::TimedOut
+        };
+        let response = response.ok().filter(|_| !rejected);
+        self.record_consumer_outcome(
+            &consumer_id,
+            &producer_id,
+            status,
+            &fee,
+            response.as_ref().and_then(|r| r.refund_amount),
+        );
+        if let Some(idempotency_key) = idempotency_key {
+            self.set_idempotent_request_status(&consumer_id, idempotency_key, request_id, status);
+        }
 
         let producer = self.producers.get(&producer_id).unwrap();
-        OracleEvent::ProducerUpdated(Producer {
-            account_id: producer.account_id.clone(),
-            requests_succeded: producer.requests_succeded,
-            requests_timed_out: producer.requests_timed_out,
-            requests_pending: LookupMap::new(b"dontcare".as_slice()),
-            fee: producer.fee.clone(),
-            send_callback: producer.send_callback,
-            name: producer.name.clone(),
-            description: producer.description.clone(),
-            example_input: producer.example_input.clone(),
-        })
-        .emit();
+        Oracle::emit_producer_updated(producer);
 
-        response.ok()
+        if let Some(callback) = callback {
+            Promise::new(callback.contract_id).function_call(
+                callback.method_name,
+                serde_json::to_vec(&serde_json::json!({
+                    "request_id": request_id,
+                    "response": response,
+                }))
+                .expect("Can't serialize callback args"),
+                NearToken::from_yoctonear(0),
+                callback.gas,
+            );
+        }
+        env::value_return(
+            &serde_json::to_vec(&response).expect("Can't serialize on_response result"),
+        );
+    }
+
+    /// Follows ownership transfers to find the current account of
+    /// a producer.
+    pub fn resolve_producer_id(&self, mut producer_id: ProducerId) -> ProducerId {
+        while let Some(new_producer_id) = self.transferred_producers.get(&producer_id) {
+            producer_id = new_producer_id.clone();
+        }
+        producer_id
     }
 }
 
 #[ext_contract(ext_oracle_responder)]
 pub trait OracleResponder {
     fn respond(&mut self, request_id: RequestId, response: Response);
+    fn acknowledge(&mut self, request_id: RequestId, extend: bool);
+    fn report_progress(
+        &mut self,
+        request_id: RequestId,
+        progress_data: String,
+        producer_id: Option<ProducerId>,
+    );
 }
 
 #[cfg(feature = "contract")]
@@ -207,19 +823,114 @@ pub trait OracleResponder {
 impl OracleResponder for Oracle {
     fn respond(&mut self, request_id: RequestId, response: Response) {
         let producer_id = env::predecessor_account_id();
+        if let Some(response_blob) = response.response_blob {
+            self.reference_blob(&producer_id, response_blob);
+        }
         let producer = self
             .producers
             .get_mut(&producer_id)
             .expect("Producer is not registered");
         if let Some(pending_request) = producer.requests_pending.remove(&request_id) {
+            if let Some(deadline_block) = pending_request.deadline_block {
+                near_sdk::require!(
+                    env::block_height() <= deadline_block,
+                    "Request deadline has passed"
+                );
+            }
+            let payload = serde_json::to_vec(&YieldResumption::Response(response))
+                .expect("Can't serialize on_response args");
+            near_sdk::require!(
+                payload.len() <= MAX_RESUME_PAYLOAD_LENGTH,
+                format!(
+                    "Response can't be longer than {MAX_RESUME_PAYLOAD_LENGTH} bytes as JSON, use response_blob instead"
+                )
+            );
+            if !env::promise_yield_resume(&pending_request.resumption_token, &payload) {
+                env::panic_str("Resumption token not found")
+            }
+        } else {
+            env::panic_str("Request not found or already responded to")
+        }
+    }
+
+    /// Lets the consumer know that the producer is working on the
+    /// request. If `extend` is true, the request gets another ~200
+    /// blocks before it times out, up to the producer's
+    /// `max_extensions` when the request was made. Requests with a
+    /// deadline can't be extended past it. The request id and the
+    /// prepaid fee stay the same.
+    fn acknowledge(&mut self, request_id: RequestId, extend: bool) {
+        let producer_id = env::predecessor_account_id();
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer is not registered");
+        let pending_request = producer
+            .requests_pending
+            .get_mut(&request_id)
+            .unwrap_or_else(|| env::panic_str("Request not found or already responded to"));
+        if extend {
+            near_sdk::require!(
+                pending_request.extensions < pending_request.max_extensions,
+                "Request can't be extended anymore"
+            );
+            if let Some(deadline_block) = pending_request.deadline_block {
+                near_sdk::require!(
+                    env::block_height().saturating_add(YIELD_TIMEOUT_BLOCKS) <= deadline_block,
+                    "Extension would go past the request deadline"
+                );
+            }
+            pending_request.extensions += 1;
             if !env::promise_yield_resume(
                 &pending_request.resumption_token,
-                &serde_json::to_vec(&response).expect("Can't serialize on_response args"),
+                &serde_json::to_vec(&YieldResumption::Extend)
+                    .expect("Can't serialize on_response args"),
             ) {
                 env::panic_str("Resumption token not found")
             }
-        } else {
-            env::panic_str("Request not found or already responded to")
         }
+        OracleEvent::RequestAcknowledged(RequestAcknowledgedEventV1 {
+            producer_id,
+            request_id,
+            extensions: pending_request.extensions,
+        })
+        .emit();
+    }
+
+    /// Stores the latest progress of a pending request and emits a
+    /// `progress` event. Can be called by the producer or one of its
+    /// operators, in which case `producer_id` is required.
+    fn report_progress(
+        &mut self,
+        request_id: RequestId,
+        progress_data: String,
+        producer_id: Option<ProducerId>,
+    ) {
+        near_sdk::require!(
+            progress_data.len() <= MAX_PROGRESS_DATA_LENGTH,
+            format!("Progress data can't be longer than {MAX_PROGRESS_DATA_LENGTH} bytes")
+        );
+        let caller = env::predecessor_account_id();
+        let producer_id = producer_id.unwrap_or_else(|| caller.clone());
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer doesn't exist");
+        near_sdk::require!(
+            caller == producer_id || producer.operators.contains(&caller),
+            "Only the producer or its operators can report progress"
+        );
+        producer
+            .requests_pending
+            .get_mut(&request_id)
+            .unwrap_or_else(|| env::panic_str("Request not found or already responded to"))
+            .progress = Some(progress_data.clone());
+
+        OracleEvent::Progress(ProgressEventV1 {
+            producer_id,
+            request_id,
+            progress_data,
+        })
+        .emit();
     }
 }
@@ -0,0 +1,163 @@
+use near_sdk::{env, json_types::U128, log, near, AccountId, Gas, NearToken, Promise};
+use near_sdk_contract_tools::ft::ext_nep141;
+
+use crate::{
+    balance::FtId,
+    consumer::{ConsumerId, OracleEvent, RequestId},
+    fees::{apply_bps, PrepaidFee},
+    producer::ProducerId,
+};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+#[near(serializers=["json"])]
+pub struct ReferralCommissionEventV1 {
+    pub referrer: AccountId,
+    pub producer_id: ProducerId,
+    pub request_id: RequestId,
+    /// `None` for NEAR.
+    pub token: Option<FtId>,
+    pub amount: U128,
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    /// Sets the share of the fee, in basis points, that is paid to
+    /// referrers of requests to the caller's producer.
+    pub fn set_referral_fee(&mut self, referral_fee_bps: u16) {
+        near_sdk::require!(
+            referral_fee_bps <= 10000,
+            "Referral fee can't be more than 10000 basis points"
+        );
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.referral_fee_bps = referral_fee_bps;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    pub fn get_referral_earnings_near(&self, account_id: AccountId) -> NearToken {
+        self.referral_earnings_near
+            .get(&account_id)
+            .cloned()
+            .unwrap_or(NearToken::from_near(0))
+    }
+
+    pub fn get_referral_earnings_ft(&self, account_id: AccountId, ft_id: FtId) -> U128 {
+        self.referral_earnings_ft
+            .get(&(account_id, ft_id))
+            .cloned()
+            .unwrap_or(U128(0))
+    }
+
+    #[payable]
+    pub fn withdraw_referral_earnings_near(&mut self) {
+        near_sdk::assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let amount = self
+            .referral_earnings_near
+            .remove(&account_id)
+            .expect("No referral earnings");
+        log!("Withdrew {amount} of referral earnings to {account_id}");
+        Promise::new(account_id).transfer(amount);
+    }
+
+    #[payable]
+    pub fn withdraw_referral_earnings_ft(&mut self, ft_id: FtId) {
+        near_sdk::assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let amount = self
+            .referral_earnings_ft
+            .remove(&(account_id.clone(), ft_id.clone()))
+            .expect("No referral earnings");
+        log!(
+            "Withdrew {amount} {ft_id} of referral earnings to {account_id}",
+            amount = amount.0
+        );
+        ext_nep141::ext(ft_id)
+            .with_static_gas(Gas::from_tgas(10))
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .ft_transfer(account_id, amount, None);
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Credits the referrer with the producer's referral share of the
+    /// fee that the producer keeps after the refund. Returns the
+    /// commission amount. Nothing is paid if the referrer is the
+    /// consumer or the producer themselves.
+    pub fn pay_referral_commission(
+        &mut self,
+        referrer: &AccountId,
+        consumer_id: &ConsumerId,
+        producer_id: &ProducerId,
+        request_id: RequestId,
+        fee: &PrepaidFee,
+        refund_amount: Option<U128>,
+    ) -> U128 {
+        if referrer == consumer_id || referrer == producer_id {
+            return U128(0);
+        }
+        let referral_fee_bps = self
+            .producers
+            .get(producer_id)
+            .expect("Producer doesn't exist")
+            .referral_fee_bps as u128;
+        let Some(payout) = fee
+            .amount()
+            .0
+            .checked_sub(refund_amount.unwrap_or(U128(0)).0)
+        else {
+            env::panic_str("Refund amount is greater than prepaid amount")
+        };
+        let commission = apply_bps(payout, referral_fee_bps);
+        if commission == 0 {
+            return U128(0);
+        }
+
+        let token = match fee {
+            PrepaidFee::None => return U128(0),
+            PrepaidFee::Near { .. } => {
+                let earnings = self
+                    .referral_earnings_near
+                    .get(referrer)
+                    .cloned()
+                    .unwrap_or(NearToken::from_near(0));
+                self.referral_earnings_near.insert(
+                    referrer.clone(),
+                    earnings
+                        .checked_add(NearToken::from_yoctonear(commission))
+                        .expect("Overflow"),
+                );
+                None
+            }
+            PrepaidFee::FungibleToken { token, .. } => {
+                let key = (referrer.clone(), token.clone());
+                let earnings = self
+                    .referral_earnings_ft
+                    .get(&key)
+                    .cloned()
+                    .unwrap_or(U128(0));
+                self.referral_earnings_ft.insert(
+                    key,
+                    earnings.0.checked_add(commission).expect("Overflow").into(),
+                );
+                Some(token.clone())
+            }
+        };
+
+        OracleEvent::ReferralCommission(ReferralCommissionEventV1 {
+            referrer: referrer.clone(),
+            producer_id: producer_id.clone(),
+            request_id,
+            token,
+            amount: U128(commission),
+        })
+        .emit();
+        U128(commission)
+    }
+}
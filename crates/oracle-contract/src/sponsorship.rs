@@ -0,0 +1,304 @@
+use near_sdk::{env, json_types::U128, log, near, EpochHeight, Gas, NearToken, Promise};
+use near_sdk_contract_tools::ft::ext_nep141;
+
+use crate::{
+    balance::FtId,
+    consumer::ConsumerId,
+    fees::{FtPaymentType, NearPaymentType, PrepaidFee, ProducerFee},
+    producer::ProducerId,
+};
+#[cfg(feature = "contract")]
+use crate::{Oracle, OracleExt};
+
+const MAX_UNLIMITED_CONSUMERS: usize = 50;
+
+/// Producers can fund a sponsorship pool to give out free usage, for
+/// example to onboard new dapps. If a consumer is eligible, the fee is
+/// paid from the pool before the consumer's balance is touched. Fees
+/// attached to the `request` call and requests with a tip are never
+/// sponsored.
+#[derive(Clone, PartialEq, Debug, Default)]
+#[near(serializers=[borsh, json])]
+pub struct SponsorshipRules {
+    /// Number of sponsored requests each consumer can make per epoch.
+    pub free_requests_per_epoch: u32,
+    /// Consumers that have unlimited sponsored requests, as long as
+    /// the pool has enough balance. Can't be more than 50.
+    pub unlimited_consumers: Vec<ConsumerId>,
+}
+
+/// Number of sponsored requests a consumer has made in an epoch.
+#[near(serializers=[borsh])]
+pub struct SponsoredUsage {
+    pub epoch_height: EpochHeight,
+    pub requests: u32,
+}
+
+#[cfg(feature = "contract")]
+#[near]
+impl Oracle {
+    pub fn set_sponsorship_rules(&mut self, rules: SponsorshipRules) {
+        near_sdk::require!(
+            rules.unlimited_consumers.len() <= MAX_UNLIMITED_CONSUMERS,
+            format!("Can't have more than {MAX_UNLIMITED_CONSUMERS} unlimited consumers")
+        );
+        let producer = self
+            .producers
+            .get_mut(&env::predecessor_account_id())
+            .expect("Producer is not registered");
+        producer.sponsorship_rules = rules;
+
+        Oracle::emit_producer_updated(producer);
+    }
+
+    #[payable]
+    pub fn deposit_sponsorship_near(&mut self, producer_id: Option<ProducerId>) {
+        let amount = env::attached_deposit();
+        let producer_id = producer_id.unwrap_or_else(env::predecessor_account_id);
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer doesn't exist");
+        producer.sponsorship_near_balance = producer
+            .sponsorship_near_balance
+            .checked_add(amount)
+            .expect("Overflow");
+        log!("Deposited {amount} to sponsorship pool of {producer_id}");
+    }
+
+    #[payable]
+    pub fn withdraw_sponsorship_near(&mut self, amount: NearToken) {
+        near_sdk::assert_one_yocto();
+        let producer_id = env::predecessor_account_id();
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer is not registered");
+        near_sdk::require!(
+            producer.sponsorship_near_balance >= amount,
+            "Not enough balance in sponsorship pool"
+        );
+        producer.sponsorship_near_balance = producer
+            .sponsorship_near_balance
+            .checked_sub(amount)
+            .expect("Underflow");
+        log!("Withdrew {amount} from sponsorship pool of {producer_id}");
+        Promise::new(producer_id).transfer(amount);
+    }
+
+    #[payable]
+    pub fn withdraw_sponsorship_ft(&mut self, amount: U128, ft_id: FtId) {
+        near_sdk::assert_one_yocto();
+        let producer_id = env::predecessor_account_id();
+        let producer = self
+            .producers
+            .get_mut(&producer_id)
+            .expect("Producer is not registered");
+        let ft_balance = producer
+            .sponsorship_ft_balances
+            .get_mut(&ft_id)
+            .expect("No balance in sponsorship pool");
+        near_sdk::require!(
+            *ft_balance >= amount,
+            "Not enough balance in sponsorship pool"
+        );
+        *ft_balance = ft_balance
+            .0
+            .checked_sub(amount.0)
+            .expect("Underflow")
+            .into();
+        if *ft_balance == 0.into() {
+            producer.sponsorship_ft_balances.remove(&ft_id);
+        }
+        log!(
+            "Withdrew {amount} {ft_id} from sponsorship pool of {producer_id}",
+            amount = amount.0
+        );
+
+        ext_nep141::ext(ft_id)
+            .with_static_gas(Gas::from_tgas(10))
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .ft_transfer(producer_id, amount, None);
+    }
+
+    pub fn get_sponsorship_rules(&self, producer_id: ProducerId) -> &SponsorshipRules {
+        &self
+            .producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist")
+            .sponsorship_rules
+    }
+
+    pub fn get_sponsorship_balance_near(&self, producer_id: ProducerId) -> NearToken {
+        self.producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist")
+            .sponsorship_near_balance
+    }
+
+    pub fn get_sponsorship_balance_ft(&self, producer_id: ProducerId, ft_id: FtId) -> U128 {
+        self.producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist")
+            .sponsorship_ft_balances
+            .get(&ft_id)
+            .cloned()
+            .unwrap_or(U128(0))
+    }
+
+    /// Returns the number of sponsored requests the consumer can still
+    /// make in the current epoch, or `None` if it's unlimited. This
+    /// doesn't check whether the pool has enough balance.
+    pub fn get_sponsored_requests_left(
+        &self,
+        producer_id: ProducerId,
+        consumer_id: ConsumerId,
+    ) -> Option<u32> {
+        let producer = self
+            .producers
+            .get(&producer_id)
+            .expect("Producer doesn't exist");
+        if producer
+            .sponsorship_rules
+            .unlimited_consumers
+            .contains(&consumer_id)
+        {
+            return None;
+        }
+        let used = producer
+            .sponsored_usage
+            .get(&consumer_id)
+            .filter(|usage| usage.epoch_height == env::epoch_height())
+            .map(|usage| usage.requests)
+            .unwrap_or(0);
+        Some(
+            producer
+                .sponsorship_rules
+                .free_requests_per_epoch
+                .saturating_sub(used),
+        )
+    }
+}
+
+#[cfg(feature = "contract")]
+impl Oracle {
+    /// Charges the fee from the producer's sponsorship pool if the
+    /// consumer is eligible and the pool has enough balance.
+    pub fn try_charge_sponsored(
+        &mut self,
+        consumer_id: &ConsumerId,
+        producer_id: &ProducerId,
+        fee: &ProducerFee,
+    ) -> Option<PrepaidFee> {
+        let producer = self
+            .producers
+            .get_mut(producer_id)
+            .expect("Producer doesn't exist");
+        let epoch_height = env::epoch_height();
+        let unlimited = producer
+            .sponsorship_rules
+            .unlimited_consumers
+            .contains(consumer_id);
+        if !unlimited {
+            let used = producer
+                .sponsored_usage
+                .get(consumer_id)
+                .filter(|usage| usage.epoch_height == epoch_height)
+                .map(|usage| usage.requests)
+                .unwrap_or(0);
+            if used >= producer.sponsorship_rules.free_requests_per_epoch {
+                return None;
+            }
+        }
+
+        let prepaid_fee = match fee {
+            ProducerFee::None => return None,
+            ProducerFee::Near { prepaid_amount } => {
+                producer.sponsorship_near_balance = producer
+                    .sponsorship_near_balance
+                    .checked_sub(*prepaid_amount)?;
+                PrepaidFee::Near {
+                    amount: *prepaid_amount,
+                    payment_type: NearPaymentType::Sponsored,
+                }
+            }
+            ProducerFee::FungibleToken {
+                token,
+                prepaid_amount,
+            } => {
+                let ft_balance = producer.sponsorship_ft_balances.get_mut(token)?;
+                let remaining = ft_balance.0.checked_sub(prepaid_amount.0)?;
+                *ft_balance = remaining.into();
+                if remaining == 0 {
+                    producer.sponsorship_ft_balances.remove(token);
+                }
+                PrepaidFee::FungibleToken {
+                    token: token.clone(),
+                    amount: *prepaid_amount,
+                    payment_type: FtPaymentType::Sponsored,
+                }
+            }
+        };
+
+        if !unlimited {
+            if let Some(usage) = producer
+                .sponsored_usage
+                .get_mut(consumer_id)
+                .filter(|usage| usage.epoch_height == epoch_height)
+            {
+                usage.requests += 1;
+            } else {
+                producer.sponsored_usage.insert(
+                    consumer_id.clone(),
+                    SponsoredUsage {
+                        epoch_height,
+                        requests: 1,
+                    },
+                );
+            }
+        }
+
+        Some(prepaid_fee)
+    }
+
+    /// Gives back the sponsored request of a fee that was refunded in
+    /// full, if it was made in the current epoch.
+    pub fn restore_sponsored_usage(&mut self, consumer_id: &ConsumerId, producer_id: &ProducerId) {
+        let producer = self
+            .producers
+            .get_mut(producer_id)
+            .expect("Producer doesn't exist");
+        if let Some(usage) = producer
+            .sponsored_usage
+            .get_mut(consumer_id)
+            .filter(|usage| usage.epoch_height == env::epoch_height())
+        {
+            usage.requests = usage.requests.saturating_sub(1);
+        }
+    }
+
+    pub fn credit_sponsorship_near(&mut self, producer_id: &ProducerId, amount: NearToken) {
+        let producer = self
+            .producers
+            .get_mut(producer_id)
+            .expect("Producer doesn't exist");
+        producer.sponsorship_near_balance = producer
+            .sponsorship_near_balance
+            .checked_add(amount)
+            .unwrap();
+    }
+
+    pub fn credit_sponsorship_ft(&mut self, producer_id: &ProducerId, token: &FtId, amount: U128) {
+        let producer = self
+            .producers
+            .get_mut(producer_id)
+            .expect("Producer doesn't exist");
+        if let Some(ft_balance) = producer.sponsorship_ft_balances.get_mut(token) {
+            *ft_balance = ft_balance.0.checked_add(amount.0).unwrap().into();
+        } else {
+            producer
+                .sponsorship_ft_balances
+                .insert(token.clone(), amount);
+        }
+    }
+}
@@ -14,6 +14,55 @@ pub async fn get_contract_wasm() -> &'static Vec<u8> {
         .await
 }
 
+/// Commit of the first version of the contract, to test upgrading
+/// from its state.
+const BASELINE_COMMIT: &str = "483982241521a45d54a5e57b27d2358a172f6b27";
+
+static BASELINE_CONTRACT_WASM: OnceCell<Vec<u8>> = OnceCell::const_new();
+
+pub async fn get_baseline_contract_wasm() -> &'static Vec<u8> {
+    BASELINE_CONTRACT_WASM
+        .get_or_init(|| async {
+            let dir = std::env::temp_dir().join(format!("intear-oracle-{BASELINE_COMMIT}"));
+            if !dir.exists() {
+                std::fs::create_dir_all(&dir).expect("creating a directory for the baseline");
+                let archive = dir.join("baseline.tar");
+                // Run from the crate's directory, so that only the crate is archived
+                let status = std::process::Command::new("git")
+                    .current_dir(env!("CARGO_MANIFEST_DIR"))
+                    .args(["archive", "--output"])
+                    .arg(&archive)
+                    .arg(BASELINE_COMMIT)
+                    .status()
+                    .expect("running git archive");
+                assert!(
+                    status.success(),
+                    "git archive failed, fetch {BASELINE_COMMIT} if it's not in the local clone"
+                );
+                let status = std::process::Command::new("tar")
+                    .arg("-xf")
+                    .arg(&archive)
+                    .arg("-C")
+                    .arg(&dir)
+                    .status()
+                    .expect("running tar");
+                assert!(status.success(), "tar failed");
+                // Build the baseline with the same dependency versions
+                // as the workspace, instead of the latest compatible ones
+                let lockfile =
+                    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../Cargo.lock");
+                if lockfile.exists() {
+                    std::fs::copy(lockfile, dir.join("Cargo.lock"))
+                        .expect("copying Cargo.lock for the baseline");
+                }
+            }
+            near_workspaces::compile_project(dir.to_str().unwrap())
+                .await
+                .expect("compiling the baseline `intear-oracle` contract for tests")
+        })
+        .await
+}
+
 static FT_CONTRACT_WASM: OnceCell<Vec<u8>> = OnceCell::const_new();
 
 pub async fn get_ft_contract_wasm() -> &'static Vec<u8> {
@@ -26,6 +75,18 @@ pub async fn get_ft_contract_wasm() -> &'static Vec<u8> {
         .await
 }
 
+static VALIDATOR_CONTRACT_WASM: OnceCell<Vec<u8>> = OnceCell::const_new();
+
+pub async fn get_validator_contract_wasm() -> &'static Vec<u8> {
+    VALIDATOR_CONTRACT_WASM
+        .get_or_init(|| async {
+            near_workspaces::compile_project("../test-validator-contract")
+                .await
+                .expect("compiling `test-validator-contract` contract for tests")
+        })
+        .await
+}
+
 static EXMAPLE_CONSUMER_CONTRACT_WASM: OnceCell<Vec<u8>> = OnceCell::const_new();
 
 pub async fn get_example_consumer_contract_wasm() -> &'static Vec<u8> {
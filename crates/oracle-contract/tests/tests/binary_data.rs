@@ -0,0 +1,85 @@
+use intear_oracle::{consumer::RequestEventV1, producer::Response};
+use near_sdk::{borsh, json_types::Base64VecU8};
+use serde_json::json;
+
+#[tokio::test]
+async fn binary_request_and_response() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "",
+            "options": {
+                "request_bytes": Base64VecU8(vec![0; 4097]),
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "",
+            "options": {
+                "request_bytes": Base64VecU8(borsh::to_vec(&(7u32, "Hello World!".to_string()))?),
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    // Too large to resume the request with
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": Response::borsh(&vec![0xffu8; 1024], None),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Response can't be longer than 1024 bytes as JSON"));
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": Response::borsh(&[0xffu8; 64], None),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let event = serde_json::from_str::<serde_json::Value>(
+        request_result.logs()[0]
+            .strip_prefix("EVENT_JSON:")
+            .unwrap(),
+    )?;
+    assert_eq!(event["event"], "request");
+    let request_event = serde_json::from_value::<RequestEventV1>(event["data"].clone())?;
+    assert_eq!(
+        request_event.parse_borsh::<(u32, String)>(),
+        Some((7, "Hello World!".to_string()))
+    );
+
+    let response = request_result.json::<Option<Response>>()?.unwrap();
+    assert_eq!(response.parse_borsh::<[u8; 64]>(), Some([0xff; 64]));
+
+    Ok(())
+}
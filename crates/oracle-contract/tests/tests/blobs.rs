@@ -0,0 +1,284 @@
+use near_sdk::{json_types::Base64VecU8, NearToken};
+use serde_json::json;
+
+#[tokio::test]
+async fn request_and_response_blobs() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let request_data = vec![1; 5000];
+    let response_data = vec![2; 5000];
+
+    // Another account uploading the same data first doesn't get in
+    // the way
+    let other_consumer_account = sandbox.dev_create_account().await?;
+    crate::common::register_consumer(&contract, &other_consumer_account).await?;
+    let outcome = other_consumer_account
+        .call(contract.id(), "upload_blob")
+        .args_json(json!({
+            "data": Base64VecU8(request_data.clone()),
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Not enough deposit for storage
+    let outcome = consumer_account
+        .call(contract.id(), "upload_blob")
+        .args_json(json!({
+            "data": Base64VecU8(request_data.clone()),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = consumer_account
+        .call(contract.id(), "upload_blob")
+        .args_json(json!({
+            "data": Base64VecU8(request_data.clone()),
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let request_blob = outcome.json::<String>()?;
+
+    // Only the account that uploaded the blob can remove it
+    let outcome = other_consumer_account
+        .call(contract.id(), "remove_blob")
+        .args_json(json!({
+            "owner": consumer_account.id(),
+            "hash": request_blob,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "See request_blob",
+            "options": {
+                "request_blob": request_blob,
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .view(contract.id(), "get_blob")
+        .args_json(json!({
+            "owner": consumer_account.id(),
+            "hash": request_blob,
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<Option<Base64VecU8>>()?,
+        Some(Base64VecU8(request_data))
+    );
+
+    let outcome = producer_account
+        .call(contract.id(), "upload_blob")
+        .args_json(json!({
+            "data": Base64VecU8(response_data.clone()),
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let response_blob = outcome.json::<String>()?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "See response_blob",
+                "response_blob": request_blob,
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "See response_blob",
+                "response_blob": response_blob,
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(
+        request_result.logs()[0],
+        format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"request\",\"data\":{{\"producer_id\":\"{producer}\",\"consumer_id\":\"{consumer}\",\"request_id\":\"0\",\"request_data\":\"See request_blob\",\"request_blob\":\"{request_blob}\"}}}}",
+            producer = producer_account.id(),
+            consumer = consumer_account.id(),
+        )
+    );
+    let response = request_result.json::<Option<serde_json::Value>>()?.unwrap();
+    assert_eq!(response["response_blob"], response_blob);
+    assert_eq!(response.get("response_blob_data"), None);
+
+    // The request blob is removed once the request completes
+    let outcome = consumer_account
+        .view(contract.id(), "get_blob")
+        .args_json(json!({
+            "owner": consumer_account.id(),
+            "hash": request_blob,
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<Base64VecU8>>()?, None);
+
+    // The response blob is kept until the consumer removes it
+    let outcome = consumer_account
+        .view(contract.id(), "get_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<Option<Base64VecU8>>()?,
+        Some(Base64VecU8(response_data))
+    );
+
+    let outcome = producer_account
+        .call(contract.id(), "remove_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = consumer_account
+        .call(contract.id(), "remove_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<Base64VecU8>>()?, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn response_blob_expires() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let other_account = sandbox.dev_create_account().await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Give me a blob",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "upload_blob")
+        .args_json(json!({
+            "data": Base64VecU8(vec![2; 5000]),
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let response_blob = outcome.json::<String>()?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "See response_blob",
+                "response_blob": response_blob,
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(request.await?.is_success());
+
+    // The consumer still holds the blob
+    let outcome = other_account
+        .call(contract.id(), "remove_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    sandbox
+        .fast_forward(intear_oracle::blobs::RESPONSE_BLOB_RETENTION_BLOCKS + 1)
+        .await?;
+
+    let producer_balance_before = producer_account.view_account().await?.balance;
+    let outcome = other_account
+        .call(contract.id(), "remove_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_blob")
+        .args_json(json!({
+            "owner": producer_account.id(),
+            "hash": response_blob,
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<Base64VecU8>>()?, None);
+
+    // The storage deposit goes back to the producer
+    sandbox.fast_forward(2).await?;
+    let producer_balance_after = producer_account.view_account().await?.balance;
+    assert!(producer_balance_after > producer_balance_before);
+
+    Ok(())
+}
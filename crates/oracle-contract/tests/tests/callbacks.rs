@@ -0,0 +1,126 @@
+use serde_json::json;
+
+/// Finds the arguments logged by `on_oracle_response` of the test
+/// validator contract.
+fn callback_args(logs: Vec<&str>) -> serde_json::Value {
+    logs.into_iter()
+        .find_map(|log| {
+            serde_json::from_str::<serde_json::Value>(log)
+                .ok()
+                .filter(|args| args.get("request_id").is_some())
+        })
+        .expect("Callback wasn't called")
+}
+
+#[tokio::test]
+async fn callbacks() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let callback_contract = sandbox
+        .dev_deploy(crate::get_validator_contract_wasm().await)
+        .await?;
+
+    let callback = json!({
+        "contract_id": callback_contract.id(),
+        "method_name": "on_oracle_response",
+        "gas": "5000000000000",
+    });
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "callback": callback,
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    let args = callback_args(request_result.logs());
+    assert_eq!(args["request_id"], "0");
+    assert_eq!(
+        args["response"]["response_data"],
+        "Hello Yielded Execution!"
+    );
+
+    // The callback is called on timeout too
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "This will timeout",
+            "options": {
+                "callback": callback,
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(250).await?;
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    let args = callback_args(request_result.logs());
+    assert_eq!(args["request_id"], "1");
+    assert_eq!(args["response"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callback_to_oracle_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        contract,
+        producer_account,
+        consumer_account,
+        ..
+    } = crate::common::setup().await?;
+
+    // The callback would be called by the oracle itself, so it could
+    // call private methods
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "callback": {
+                    "contract_id": contract.id(),
+                    "method_name": "add_producer",
+                    "gas": "5000000000000",
+                },
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Callback can't be the oracle contract"));
+
+    Ok(())
+}
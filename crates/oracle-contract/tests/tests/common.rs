@@ -0,0 +1,94 @@
+//! Setup shared by the tests: a freshly deployed oracle with one
+//! producer and one registered consumer.
+
+use intear_oracle::fees::ProducerFee;
+use near_sdk::NearToken;
+use near_workspaces::{network::Sandbox, Account, Contract, Worker};
+use serde_json::json;
+
+pub struct Env {
+    pub sandbox: Worker<Sandbox>,
+    pub contract: Contract,
+    pub producer_account: Account,
+    pub consumer_account: Account,
+}
+
+pub async fn setup() -> Result<Env, Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract_wasm = crate::get_contract_wasm().await;
+
+    let contract = sandbox.dev_deploy(contract_wasm).await?;
+
+    let producer_account = sandbox.dev_create_account().await?;
+    let consumer_account = sandbox.dev_create_account().await?;
+
+    add_producer(&contract, &producer_account).await?;
+    register_consumer(&contract, &consumer_account).await?;
+
+    Ok(Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    })
+}
+
+pub async fn add_producer(
+    contract: &Contract,
+    producer_account: &Account,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = producer_account
+        .call(contract.id(), "add_producer")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    Ok(())
+}
+
+pub async fn register_consumer(
+    contract: &Contract,
+    consumer_account: &Account,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = consumer_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    Ok(())
+}
+
+pub async fn set_near_fee(
+    contract: &Contract,
+    producer_account: &Account,
+    prepaid_amount: NearToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = producer_account
+        .call(contract.id(), "set_fee")
+        .args_json(json!({
+            "fee": ProducerFee::Near { prepaid_amount },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    Ok(())
+}
+
+pub async fn deposit_near(
+    contract: &Contract,
+    consumer_account: &Account,
+    amount: NearToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({}))
+        .deposit(amount)
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn consumer_details() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+                "refund_amount": NearToken::from_millinear(30).as_yoctonear().to_string(),
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    // Make a request but don't respond to it
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "This will timeout",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(250).await?;
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_consumer_details")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<serde_json::Value>().unwrap(),
+        json!({
+            "account_id": consumer_account.id(),
+            "requests_succeeded": 1,
+            "requests_timed_out": 1,
+            "requests_rejected": 0,
+            "requests_pending": 0,
+            "near_spent": NearToken::from_millinear(100 - 30),
+            "ft_spent": [],
+            "producers": [
+                {
+                    "producer_id": producer_account.id(),
+                    "requests_succeeded": 1,
+                    "requests_timed_out": 1,
+                    "requests_rejected": 0,
+                },
+            ],
+        })
+    );
+
+    Ok(())
+}
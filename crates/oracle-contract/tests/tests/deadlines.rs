@@ -0,0 +1,167 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn deadlines() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let keeper_account = sandbox.dev_create_account().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "deadline_blocks": 20,
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = keeper_account
+        .call(contract.id(), "expire")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_id": "0",
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    sandbox.fast_forward(30).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Too late",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = keeper_account
+        .call(contract.id(), "expire")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_id": "0",
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(request_result.json::<Option<serde_json::Value>>()?, None);
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(1)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn deadline_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "deadline_blocks": u64::MAX,
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Deadline is too far in the future"));
+
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 1,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "deadline_blocks": 100,
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    // Another yield would end after the deadline
+    let outcome = producer_account
+        .call(contract.id(), "acknowledge")
+        .args_json(json!({
+            "request_id": "0",
+            "extend": true,
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Extension would go past the request deadline"));
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Just in time",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(request.await?.is_success());
+
+    Ok(())
+}
@@ -9,16 +9,17 @@ async fn near_deposits() -> Result<(), Box<dyn std::error::Error>> {
     let contract = sandbox.dev_deploy(contract_wasm).await?;
 
     let consumer_account = sandbox.dev_create_account().await?;
-    let initial_balance = consumer_account.view_account().await?.balance;
 
     let outcome = consumer_account
         .call(contract.id(), "register_consumer")
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
+    let initial_balance = consumer_account.view_account().await?.balance;
 
     let outcome = consumer_account
         .view(contract.id(), "get_deposit_near")
@@ -100,6 +101,7 @@ async fn near_deposits() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "amount": NearToken::from_near(1),
         }))
+        .deposit(NearToken::from_yoctonear(1))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -144,16 +146,17 @@ async fn near_deposit_for_producer() -> Result<(), Box<dyn std::error::Error>> {
 
     let consumer_account = sandbox.dev_create_account().await?;
     let producer_account = sandbox.dev_create_account().await?;
-    let initial_balance = consumer_account.view_account().await?.balance;
 
     let outcome = consumer_account
         .call(contract.id(), "register_consumer")
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
+    let initial_balance = consumer_account.view_account().await?.balance;
 
     let outcome = consumer_account
         .call(contract.id(), "deposit_near")
@@ -246,6 +249,7 @@ async fn ft_deposits() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -354,6 +358,7 @@ async fn ft_deposits() -> Result<(), Box<dyn std::error::Error>> {
             "ft_id": token.id(),
             "amount": "500000000000000000000000",
         }))
+        .deposit(NearToken::from_yoctonear(1))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -413,6 +418,7 @@ async fn ft_deposit_for_producer() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -525,3 +531,218 @@ async fn ft_deposit_for_producer() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn balance_transfers() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract_wasm = crate::get_contract_wasm().await;
+
+    let contract = sandbox.dev_deploy(contract_wasm).await?;
+
+    let consumer_account = sandbox.dev_create_account().await?;
+    let other_consumer_account = sandbox.dev_create_account().await?;
+    let producer_account = sandbox.dev_create_account().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "add_producer")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    for account in [&consumer_account, &other_consumer_account] {
+        let outcome = account
+            .call(contract.id(), "register_consumer")
+            .args_json(json!({
+                "account_id": account.id(),
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "transfer_balance")
+        .args_json(json!({
+            "from_bucket": "ForAllProducers",
+            "to_bucket": {
+                "ForSpecificProducer": {
+                    "producer_id": producer_account.id(),
+                },
+            },
+            "amount": NearToken::from_millinear(400).as_yoctonear().to_string(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"balance_transfer\",\"data\":{{\"from_account_id\":\"{consumer}\",\"from_bucket\":\"ForAllProducers\",\"to_account_id\":\"{consumer}\",\"to_bucket\":{{\"ForSpecificProducer\":{{\"producer_id\":\"{producer}\"}}}},\"token\":null,\"amount\":\"400000000000000000000000\"}}}}",
+            consumer = consumer_account.id(),
+            producer = producer_account.id(),
+        )]
+    );
+
+    let outcome = consumer_account
+        .call(contract.id(), "transfer_balance")
+        .args_json(json!({
+            "from_bucket": {
+                "ForSpecificProducer": {
+                    "producer_id": producer_account.id(),
+                },
+            },
+            "to_bucket": "ForAllProducers",
+            "to_account": other_consumer_account.id(),
+            "amount": NearToken::from_millinear(100).as_yoctonear().to_string(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "transfer_balance")
+        .args_json(json!({
+            "from_bucket": {
+                "ForSpecificProducer": {
+                    "producer_id": producer_account.id(),
+                },
+            },
+            "to_bucket": "ForAllProducers",
+            "amount": NearToken::from_near(1).as_yoctonear().to_string(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    for (account, producer_id, expected) in [
+        (&consumer_account, None, NearToken::from_millinear(600)),
+        (
+            &consumer_account,
+            Some(producer_account.id()),
+            NearToken::from_millinear(300),
+        ),
+        (
+            &other_consumer_account,
+            None,
+            NearToken::from_millinear(100),
+        ),
+    ] {
+        let outcome = account
+            .view(contract.id(), "get_deposit_near")
+            .args_json(json!({
+                "account_id": account.id(),
+                "producer_id": producer_id,
+            }))
+            .await?;
+        assert_eq!(outcome.json::<NearToken>().unwrap(), expected);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn near_deposit_with_allocations() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract_wasm = crate::get_contract_wasm().await;
+
+    let contract = sandbox.dev_deploy(contract_wasm).await?;
+
+    let consumer_account = sandbox.dev_create_account().await?;
+    let producer_account = sandbox.dev_create_account().await?;
+    let other_producer_account = sandbox.dev_create_account().await?;
+
+    for account in [&producer_account, &other_producer_account] {
+        let outcome = account
+            .call(contract.id(), "add_producer")
+            .args_json(json!({}))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    let outcome = consumer_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Allocations exceed the deposit
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({
+            "allocations": [
+                [producer_account.id(), NearToken::from_millinear(600)],
+                [other_producer_account.id(), NearToken::from_millinear(600)],
+            ],
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({
+            "allocations": [
+                [producer_account.id(), NearToken::from_millinear(300)],
+                [other_producer_account.id(), NearToken::from_millinear(200)],
+            ],
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![
+            format!(
+                "Deposited 0.30 NEAR to {consumer_id} for {producer_id}",
+                consumer_id = consumer_account.id(),
+                producer_id = producer_account.id()
+            ),
+            format!(
+                "Deposited 0.20 NEAR to {consumer_id} for {producer_id}",
+                consumer_id = consumer_account.id(),
+                producer_id = other_producer_account.id()
+            ),
+            format!(
+                "Deposited 0.50 NEAR to {consumer_id}",
+                consumer_id = consumer_account.id()
+            ),
+        ]
+    );
+
+    for (producer_id, expected) in [
+        (None, NearToken::from_millinear(500)),
+        (Some(producer_account.id()), NearToken::from_millinear(300)),
+        (
+            Some(other_producer_account.id()),
+            NearToken::from_millinear(200),
+        ),
+    ] {
+        let outcome = consumer_account
+            .view(contract.id(), "get_deposit_near")
+            .args_json(json!({
+                "account_id": consumer_account.id(),
+                "producer_id": producer_id,
+            }))
+            .await?;
+        assert_eq!(outcome.json::<NearToken>().unwrap(), expected);
+    }
+
+    Ok(())
+}
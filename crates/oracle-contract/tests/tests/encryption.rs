@@ -0,0 +1,78 @@
+use near_sdk::json_types::Base64VecU8;
+use serde_json::json;
+
+#[tokio::test]
+async fn encryption_key() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract_wasm = crate::get_contract_wasm().await;
+
+    let contract = sandbox.dev_deploy(contract_wasm).await?;
+
+    let producer_account = sandbox.dev_create_account().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "add_producer")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_encryption_key")
+        .args_json(json!({
+            "encryption_public_key": Base64VecU8(vec![1; 31]),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_encryption_key")
+        .args_json(json!({
+            "encryption_public_key": Base64VecU8(vec![1; 32]),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(
+        details["encryption_public_key"],
+        json!(Base64VecU8(vec![1; 32]))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn envelope_roundtrip() {
+    use intear_oracle::encryption::{EncryptedEnvelope, EncryptionSecretKey};
+
+    let producer_secret_key = EncryptionSecretKey::from_bytes([7; 32]);
+
+    let (request_envelope, consumer_secret_key) =
+        EncryptedEnvelope::seal(&producer_secret_key.public_key(), b"Hello World!");
+    let request_data = request_envelope.to_data();
+    assert!(!request_data.contains("Hello World!"));
+
+    let request_envelope = EncryptedEnvelope::from_data(&request_data).unwrap();
+    assert_eq!(
+        request_envelope.open(&producer_secret_key).unwrap(),
+        b"Hello World!"
+    );
+    assert_eq!(request_envelope.open(&consumer_secret_key), None);
+
+    let response_envelope = request_envelope
+        .seal_reply(b"Hello Encrypted Execution!")
+        .unwrap();
+    assert_eq!(
+        response_envelope.open(&consumer_secret_key).unwrap(),
+        b"Hello Encrypted Execution!"
+    );
+}
@@ -0,0 +1,64 @@
+use serde_json::json;
+
+#[tokio::test]
+async fn end_user() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "end_user": "a".repeat(1025),
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "end_user": "alice",
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Alice!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(
+        request_result.logs()[0],
+        format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"request\",\"data\":{{\"producer_id\":\"{producer}\",\"consumer_id\":\"{consumer}\",\"request_id\":\"0\",\"request_data\":\"Hello World!\",\"end_user\":\"alice\"}}}}",
+            producer = producer_account.id(),
+            consumer = consumer_account.id(),
+        )
+    );
+
+    Ok(())
+}
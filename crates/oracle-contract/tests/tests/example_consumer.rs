@@ -70,6 +70,21 @@ async fn example_consumer_is_operational() -> Result<(), Box<dyn std::error::Err
     let request_result = request_yes.await?;
     assert!(!request_result.clone().into_result()?.json::<bool>()?);
 
+    let outcome = producer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    let latency = details["latency"]["average_latency_blocks"]
+        .as_u64()
+        .unwrap();
+    let last_response_timestamp = details["latency"]["last_response_timestamp_nanos"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
     let logs = request_result.logs();
     assert_eq!(logs, vec![
         format!("EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"request\",\"data\":{{\"producer_id\":\"{producer}\",\"consumer_id\":\"{consumer}\",\"request_id\":\"0\",\"request_data\":\"Your job is to determine if the following statement is true:\\n\\n```\\nIs slime solid?\\n```\\n\\nRespond with only \\\"Yes\\\" or \\\"No\\\"\"}}}}",
@@ -80,7 +95,7 @@ async fn example_consumer_is_operational() -> Result<(), Box<dyn std::error::Err
             producer = producer_account.id()
         ),
         format!(
-            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_updated\",\"data\":{{\"account_id\":\"{producer}\",\"requests_succeded\":1,\"requests_timed_out\":0,\"fee\":{{\"Near\":{{\"prepaid_amount\":\"10000000000000000000000\"}}}},\"send_callback\":false,\"name\":\"Unnamed\",\"description\":\"No description\",\"example_input\":null}}}}",
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_updated\",\"data\":{{\"account_id\":\"{producer}\",\"requests_succeded\":1,\"requests_timed_out\":0,\"fee\":{{\"Near\":{{\"prepaid_amount\":\"10000000000000000000000\"}}}},\"send_callback\":false,\"name\":\"Unnamed\",\"description\":\"No description\",\"example_input\":null,\"sponsorship_rules\":{{\"free_requests_per_epoch\":0,\"unlimited_consumers\":[]}},\"volume_tiers\":[],\"beneficiaries\":[],\"referral_fee_bps\":0,\"latency\":{{\"average_latency_blocks\":{latency},\"p95_latency_blocks\":{latency},\"last_response_timestamp_nanos\":\"{last_response_timestamp}\"}},\"max_pending_requests\":null,\"rate_limit\":null,\"operators\":[],\"last_heartbeat_block\":null,\"liveness_threshold_blocks\":null,\"max_extensions\":0,\"encryption_public_key\":null,\"requests_rejected\":0,\"accepts_validators\":false}}}}",
             producer = producer_account.id()
         ),
     ]);
@@ -0,0 +1,204 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn extended_request() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 1,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "This takes a while",
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(150).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "acknowledge")
+        .args_json(json!({
+            "request_id": "0",
+            "extend": true,
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"request_acknowledged\",\"data\":{{\"producer_id\":\"{producer}\",\"request_id\":\"0\",\"extensions\":1}}}}",
+            producer = producer_account.id(),
+        )]
+    );
+
+    // Would have timed out without the extension
+    sandbox.fast_forward(150).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "acknowledge")
+        .args_json(json!({
+            "request_id": "0",
+            "extend": true,
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Done!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    let response = request_result.json::<Option<serde_json::Value>>()?.unwrap();
+    assert_eq!(response["response_data"], "Done!");
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["requests_succeded"], 1);
+    assert_eq!(details["requests_timed_out"], 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn extensions_run_out() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 2,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "This takes forever",
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    // Gas was only reserved for 2 extensions
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 10,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    for _ in 0..2 {
+        sandbox.fast_forward(150).await?;
+
+        let outcome = producer_account
+            .call(contract.id(), "acknowledge")
+            .args_json(json!({
+                "request_id": "0",
+                "extend": true,
+            }))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    sandbox.fast_forward(150).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "acknowledge")
+        .args_json(json!({
+            "request_id": "0",
+            "extend": true,
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    sandbox.fast_forward(250).await?;
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(request_result.json::<Option<serde_json::Value>>()?, None);
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(1)
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_pending_requests_count")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<u64>().unwrap(), 0);
+
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 11,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use intear_oracle::fees::ProducerFee;
+use intear_oracle::fees::{Beneficiary, ProducerFee};
 use near_sdk::{json_types::U128, NearToken};
 use near_workspaces::result::ValueOrReceiptId;
 use serde_json::json;
@@ -51,6 +51,7 @@ async fn no_fee() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -201,6 +202,7 @@ async fn near_fee() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -361,6 +363,7 @@ async fn near_fee_refund() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -559,6 +562,128 @@ async fn direct_near_fee() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn near_fee_beneficiaries() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox_with_version("2.4.0").await?;
+    let contract_wasm = crate::get_contract_wasm().await;
+    let contract = sandbox.dev_deploy(contract_wasm).await?;
+
+    let producer_account = sandbox.dev_create_account().await?;
+    let data_provider_account = sandbox.dev_create_account().await?;
+    let node_operator_account = sandbox.dev_create_account().await?;
+    let data_provider_initial_balance = data_provider_account.view_account().await?.balance;
+    let node_operator_initial_balance = node_operator_account.view_account().await?.balance;
+
+    let outcome = producer_account
+        .call(contract.id(), "add_producer")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_fee")
+        .args_json(json!({
+            "fee": ProducerFee::Near {
+                prepaid_amount: NearToken::from_millinear(100), // 0.1 NEAR
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_beneficiaries")
+        .args_json(json!({
+            "beneficiaries": [
+                Beneficiary {
+                    account_id: data_provider_account.id().clone(),
+                    share_bps: 5000,
+                },
+            ],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure()); // shares don't add up to 100%
+
+    let outcome = producer_account
+        .call(contract.id(), "set_beneficiaries")
+        .args_json(json!({
+            "beneficiaries": [
+                Beneficiary {
+                    account_id: data_provider_account.id().clone(),
+                    share_bps: 7000,
+                },
+                Beneficiary {
+                    account_id: node_operator_account.id().clone(),
+                    share_bps: 3000,
+                },
+            ],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let consumer_account = sandbox.dev_create_account().await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .deposit(NearToken::from_millinear(100)) // 0.1 NEAR
+        .transact_async()
+        .await
+        .unwrap();
+
+    sandbox.fast_forward(1).await?;
+
+    // Pending requests are paid to the beneficiaries at the time of the request
+    let outcome = producer_account
+        .call(contract.id(), "set_beneficiaries")
+        .args_json(json!({
+            "beneficiaries": [],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello World",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    assert!(request.await?.is_success());
+
+    sandbox.fast_forward(2).await?;
+
+    let data_provider_new_balance = data_provider_account.view_account().await?.balance;
+    assert_eq!(
+        data_provider_new_balance
+            .checked_sub(data_provider_initial_balance)
+            .unwrap(),
+        NearToken::from_millinear(70)
+    );
+    let node_operator_new_balance = node_operator_account.view_account().await?.balance;
+    assert_eq!(
+        node_operator_new_balance
+            .checked_sub(node_operator_initial_balance)
+            .unwrap(),
+        NearToken::from_millinear(30)
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn ft_fee() -> Result<(), Box<dyn std::error::Error>> {
     let sandbox = near_workspaces::sandbox().await?;
@@ -628,6 +753,7 @@ async fn ft_fee() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -831,6 +957,7 @@ async fn ft_fee_refund() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -0,0 +1,122 @@
+use serde_json::json;
+
+#[tokio::test]
+async fn gas() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_request_gas_budget")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "callback_gas": "5000000000000",
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<serde_json::Value>()?,
+        json!({
+            "min_on_response_gas": "5000000000000",
+            "max_on_response_gas": "200000000000000",
+            "request_gas": "20000000000000",
+        })
+    );
+
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 2,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_request_gas_budget")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "callback_gas": "5000000000000",
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<serde_json::Value>()?,
+        json!({
+            "min_on_response_gas": "5000000000000",
+            "max_on_response_gas": "190000000000000",
+            "request_gas": "30000000000000",
+        })
+    );
+
+    let outcome = producer_account
+        .call(contract.id(), "set_max_extensions")
+        .args_json(json!({
+            "max_extensions": 0,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "on_response_gas": "1000000000000",
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "on_response_gas": "250000000000000",
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "on_response_gas": "50000000000000",
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    Ok(())
+}
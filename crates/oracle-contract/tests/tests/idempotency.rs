@@ -0,0 +1,184 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn idempotency() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "idempotency_key": "order-1",
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    // A retry returns the existing request and isn't charged
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "idempotency_key": "order-1",
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let existing = outcome.json::<serde_json::Value>()?;
+    assert_eq!(existing["request_id"], json!("0"));
+    assert_eq!(existing["status"], json!("Pending"));
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100)
+    );
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_idempotent_request")
+        .args_json(json!({
+            "consumer_id": consumer_account.id(),
+            "idempotency_key": "order-1",
+        }))
+        .await?;
+    let existing = outcome.json::<serde_json::Value>()?;
+    assert_eq!(existing["request_id"], json!("0"));
+    assert_eq!(existing["status"], json!("Succeeded"));
+
+    // Reusing the key for a different request fails
+    for (producer_id, request_data) in [
+        (producer_account.id(), "Hello Mars!"),
+        (consumer_account.id(), "Hello World!"),
+    ] {
+        let outcome = consumer_account
+            .call(contract.id(), "request")
+            .args_json(json!({
+                "producer_id": producer_id,
+                "request_data": request_data,
+                "options": {
+                    "idempotency_key": "order-1",
+                },
+            }))
+            .transact()
+            .await?;
+        assert!(outcome.is_failure());
+    }
+
+    // A different key creates a new request
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "idempotency_key": "order-2",
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "1",
+            "response": {
+                "response_data": "Hello again!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    // Once the window has passed, the key can be used for another request
+    sandbox.fast_forward(1000).await?;
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_idempotent_request")
+        .args_json(json!({
+            "consumer_id": consumer_account.id(),
+            "idempotency_key": "order-1",
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<serde_json::Value>>()?, None);
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello Mars!",
+            "options": {
+                "idempotency_key": "order-1",
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "2",
+            "response": {
+                "response_data": "Hello Mars!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(
+        request_result.json::<Option<serde_json::Value>>()?.unwrap()["response_data"],
+        "Hello Mars!"
+    );
+
+    Ok(())
+}
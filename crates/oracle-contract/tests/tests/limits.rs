@@ -0,0 +1,174 @@
+use intear_oracle::limits::RateLimit;
+use serde_json::json;
+
+#[tokio::test]
+async fn request_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_request_limits")
+        .args_json(json!({
+            "max_pending_requests": 1,
+            "rate_limit": RateLimit {
+                max_requests: 2,
+                period_blocks: 10000,
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    for request_id in 0..2 {
+        let request = consumer_account
+            .call(contract.id(), "request")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "request_data": "Hello World!",
+            }))
+            .transact_async()
+            .await?;
+
+        sandbox.fast_forward(1).await?;
+
+        let outcome = consumer_account
+            .view(contract.id(), "get_pending_capacity_left")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+            }))
+            .await?;
+        assert_eq!(outcome.json::<Option<u64>>().unwrap(), Some(0));
+
+        let outcome = consumer_account
+            .call(contract.id(), "request")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "request_data": "Hello World!",
+            }))
+            .transact()
+            .await?;
+        assert!(format!("{:?}", outcome.into_result().unwrap_err())
+            .contains("Producer has too many pending requests"));
+
+        let outcome = producer_account
+            .call(contract.id(), "respond")
+            .args_json(json!({
+                "request_id": request_id.to_string(),
+                "response": {
+                    "response_data": "Hello Yielded Execution!",
+                }
+            }))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+
+        let request_result = request.await?;
+        assert!(request_result.is_success());
+
+        let outcome = consumer_account
+            .view(contract.id(), "get_pending_capacity_left")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+            }))
+            .await?;
+        assert_eq!(outcome.json::<Option<u64>>().unwrap(), Some(1));
+
+        let outcome = consumer_account
+            .view(contract.id(), "get_rate_limit_left")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "consumer_id": consumer_account.id(),
+            }))
+            .await?;
+        assert_eq!(outcome.json::<Option<u32>>().unwrap(), Some(1 - request_id));
+    }
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Consumer exceeded the rate limit of this producer"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rate_limit_with_long_period() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_request_limits")
+        .args_json(json!({
+            "max_pending_requests": null,
+            "rate_limit": RateLimit {
+                max_requests: 1,
+                period_blocks: u64::MAX,
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(request.await?.is_success());
+
+    // The window never ends
+    let outcome = consumer_account
+        .view(contract.id(), "get_rate_limit_left")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "consumer_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<u32>>().unwrap(), Some(0));
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Consumer exceeded the rate limit of this producer"));
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use serde_json::json;
+
+#[tokio::test]
+async fn liveness_gating() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let operator_account = sandbox.dev_create_account().await?;
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["is_online"], serde_json::Value::Null);
+
+    let outcome = producer_account
+        .call(contract.id(), "set_operators")
+        .args_json(json!({
+            "operators": [operator_account.id()],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_liveness_threshold")
+        .args_json(json!({
+            "liveness_threshold_blocks": 10,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // No heartbeat yet
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err()).contains("Producer is offline"));
+
+    // Only the producer and its operators can send heartbeats
+    let outcome = consumer_account
+        .call(contract.id(), "heartbeat")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = operator_account
+        .call(contract.id(), "heartbeat")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["is_online"], true);
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    sandbox.fast_forward(20).await?;
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["is_online"], false);
+
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", outcome.into_result().unwrap_err()).contains("Producer is offline"));
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use intear_oracle::fees::ProducerFee;
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn migrate_from_baseline() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let baseline_wasm = crate::get_baseline_contract_wasm().await;
+    let contract_wasm = crate::get_contract_wasm().await;
+
+    let contract = sandbox.dev_deploy(baseline_wasm).await?;
+
+    let producer_account = sandbox.dev_create_account().await?;
+    let consumer_account = sandbox.dev_create_account().await?;
+
+    // Set up some state with the baseline contract
+    crate::common::add_producer(&contract, &producer_account).await?;
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    let outcome = consumer_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({}))
+        .deposit(NearToken::from_millinear(500))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    // Upgrade
+    contract
+        .as_account()
+        .deploy(contract_wasm)
+        .await?
+        .into_result()?;
+
+    let outcome = consumer_account
+        .call(contract.id(), "migrate")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = contract
+        .call("migrate")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = contract
+        .call("migrate_producers")
+        .args_json(json!({
+            "account_ids": [producer_account.id()],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The producer balance is missed the first time
+    let outcome = contract
+        .call("migrate_consumers")
+        .args_json(json!({
+            "consumers": [
+                {
+                    "account_id": consumer_account.id(),
+                },
+            ],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(0)
+    );
+
+    // and recovered by passing it again
+    let outcome = contract
+        .call("migrate_consumers")
+        .args_json(json!({
+            "consumers": [
+                {
+                    "account_id": consumer_account.id(),
+                    "producer_ids": [producer_account.id()],
+                },
+            ],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![format!(
+            "Consumer {consumer_id} is already migrated",
+            consumer_id = consumer_account.id()
+        )]
+    );
+
+    // Migrating again does nothing
+    let outcome = contract
+        .call("migrate_producers")
+        .args_json(json!({
+            "account_ids": [producer_account.id()],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![format!(
+            "Producer {producer_id} is already migrated",
+            producer_id = producer_account.id()
+        )]
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>()?;
+    assert_eq!(details["requests_succeded"], 1);
+    assert_eq!(
+        serde_json::from_value::<ProducerFee>(details["fee"].clone())?,
+        ProducerFee::Near {
+            prepaid_amount: NearToken::from_millinear(100),
+        }
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100)
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(500)
+    );
+
+    // Requests continue from the old request ID
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello again!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "1",
+            "response": {
+                "response_data": "Hello Upgraded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(
+        request_result.json::<Option<serde_json::Value>>()?.unwrap()["response_data"],
+        "Hello Upgraded Execution!"
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100 - 100)
+    );
+
+    Ok(())
+}
@@ -1,6 +1,28 @@
+mod binary_data;
+mod blobs;
+mod callbacks;
+mod common;
+mod consumer_details;
+mod deadlines;
 mod deposits;
+mod encryption;
+mod end_user;
 mod example_consumer;
+mod extensions;
 mod fees;
+mod gas;
+mod idempotency;
+mod limits;
+mod liveness;
+mod migration;
+mod pipeline;
+mod producer_transfer;
+mod progress;
+mod referral;
 mod register;
 mod request_response;
+mod sponsorship;
+mod tips;
 mod two_responses;
+mod validators;
+mod volume_tiers;
@@ -0,0 +1,173 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn pipeline() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account: http_producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let llm_producer_account = sandbox.dev_create_account().await?;
+    crate::common::add_producer(&contract, &llm_producer_account).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request_pipeline")
+        .args_json(json!({
+            "steps": [
+                {
+                    "producer_id": http_producer_account.id(),
+                    "template": "https://example.com",
+                },
+                {
+                    "producer_id": llm_producer_account.id(),
+                    "template": "{\"prompt\": \"Summarize: {{response_data}}\"}",
+                },
+            ],
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = http_producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Example \"Domain\"",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    sandbox.fast_forward(4).await?;
+
+    let outcome = llm_producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "1",
+            "response": {
+                "response_data": "A domain for examples",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    let request_event = request_result
+        .logs()
+        .into_iter()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .find(|event| event["event"] == "request" && event["data"]["request_id"] == "1")
+        .unwrap();
+    // The response is escaped, so the template is still valid JSON
+    let prompt = serde_json::from_str::<serde_json::Value>(
+        request_event["data"]["request_data"].as_str().unwrap(),
+    )?;
+    assert_eq!(prompt["prompt"], "Summarize: Example \"Domain\"");
+    let result = request_result.json::<serde_json::Value>()?;
+    assert_eq!(result["response"]["response_data"], "A domain for examples");
+    assert_eq!(result["failed_step"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pipeline_step_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account: http_producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let llm_producer_account = sandbox.dev_create_account().await?;
+    crate::common::add_producer(&contract, &llm_producer_account).await?;
+    for producer_account in [&http_producer_account, &llm_producer_account] {
+        crate::common::set_near_fee(&contract, producer_account, NearToken::from_millinear(100))
+            .await?;
+    }
+
+    // Only enough for the first step
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_millinear(150))
+        .await?;
+    let http_producer_balance_before = http_producer_account.view_account().await?.balance;
+
+    let request = consumer_account
+        .call(contract.id(), "request_pipeline")
+        .args_json(json!({
+            "steps": [
+                {
+                    "producer_id": http_producer_account.id(),
+                    "template": "https://example.com",
+                },
+                {
+                    "producer_id": llm_producer_account.id(),
+                    "template": "Summarize: {{response_data}}",
+                },
+            ],
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = http_producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Example Domain",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    let result = request_result.json::<serde_json::Value>()?;
+    assert_eq!(result["response"]["response_data"], "Example Domain");
+    assert_eq!(result["failed_step"], 1);
+
+    // The first step is paid for, the second one is never charged
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(50)
+    );
+    let http_producer_balance_after = http_producer_account.view_account().await?.balance;
+    assert!(
+        http_producer_balance_after
+            > http_producer_balance_before
+                .checked_add(NearToken::from_millinear(90))
+                .unwrap()
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_pending_requests_count")
+        .args_json(json!({
+            "producer_id": llm_producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<u64>().unwrap(), 0);
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use serde_json::json;
+
+#[tokio::test]
+async fn producer_transfer() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account: old_producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let new_producer_account = sandbox.dev_create_account().await?;
+
+    let outcome = old_producer_account
+        .call(contract.id(), "edit_producer_details")
+        .args_json(json!({
+            "name": "Transferred",
+            "description": "Transferred producer",
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": old_producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    // Can't accept a transfer that wasn't proposed
+    let outcome = new_producer_account
+        .call(contract.id(), "accept_producer_transfer")
+        .args_json(json!({
+            "producer_id": old_producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = old_producer_account
+        .call(contract.id(), "propose_producer_transfer")
+        .args_json(json!({
+            "new_account_id": new_producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = new_producer_account
+        .call(contract.id(), "accept_producer_transfer")
+        .args_json(json!({
+            "producer_id": old_producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs()[0],
+        format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_transferred\",\"data\":{{\"old_producer_id\":\"{old_producer}\",\"new_producer_id\":\"{new_producer}\"}}}}",
+            old_producer = old_producer_account.id(),
+            new_producer = new_producer_account.id(),
+        )
+    );
+
+    for (account, is_producer) in [
+        (&old_producer_account, false),
+        (&new_producer_account, true),
+    ] {
+        let outcome = consumer_account
+            .view(contract.id(), "is_producer")
+            .args_json(json!({
+                "account_id": account.id(),
+            }))
+            .await?;
+        assert_eq!(outcome.json::<bool>().unwrap(), is_producer);
+    }
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": new_producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["name"], "Transferred");
+    assert_eq!(details["account_id"], new_producer_account.id().to_string());
+
+    let outcome = old_producer_account
+        .call(contract.id(), "add_producer")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    // The pending request was moved to the new account
+    let outcome = new_producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": new_producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["requests_succeded"], 1);
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use serde_json::json;
+
+#[tokio::test]
+async fn progress_updates() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let operator_account = sandbox.dev_create_account().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_operators")
+        .args_json(json!({
+            "operators": [operator_account.id()],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = operator_account
+        .call(contract.id(), "report_progress")
+        .args_json(json!({
+            "request_id": "0",
+            "progress_data": "50%",
+            "producer_id": producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"progress\",\"data\":{{\"producer_id\":\"{producer}\",\"request_id\":\"0\",\"progress_data\":\"50%\"}}}}",
+            producer = producer_account.id(),
+        )]
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_request_progress")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_id": "0",
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<Option<String>>().unwrap(),
+        Some("50%".to_string())
+    );
+
+    // Reporting progress doesn't resolve the request
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let outcome = operator_account
+        .call(contract.id(), "report_progress")
+        .args_json(json!({
+            "request_id": "0",
+            "progress_data": "100%",
+            "producer_id": producer_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    Ok(())
+}
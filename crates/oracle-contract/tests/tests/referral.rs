@@ -0,0 +1,133 @@
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn referral_commission() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let referrer_account = sandbox.dev_create_account().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_referral_fee")
+        .args_json(json!({
+            "referral_fee_bps": 1000, // 10%
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "referrer": referrer_account.id(),
+            },
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+                "refund_amount": NearToken::from_millinear(50).as_yoctonear().to_string(), // 0.05 NEAR
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    let logs = request_result.logs();
+    assert_eq!(
+        logs[0],
+        format!(
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"request\",\"data\":{{\"producer_id\":\"{producer}\",\"consumer_id\":\"{consumer}\",\"request_id\":\"0\",\"request_data\":\"Hello World!\",\"referrer\":\"{referrer}\"}}}}",
+            producer = producer_account.id(),
+            consumer = consumer_account.id(),
+            referrer = referrer_account.id(),
+        )
+    );
+    assert!(logs.contains(&format!(
+        "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"referral_commission\",\"data\":{{\"referrer\":\"{referrer}\",\"producer_id\":\"{producer}\",\"request_id\":\"0\",\"token\":null,\"amount\":\"5000000000000000000000\"}}}}",
+        producer = producer_account.id(),
+        referrer = referrer_account.id(),
+    )));
+
+    // 10% of the 0.05 NEAR that the producer kept
+    let outcome = referrer_account
+        .view(contract.id(), "get_referral_earnings_near")
+        .args_json(json!({
+            "account_id": referrer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(5)
+    );
+
+    let outcome = referrer_account
+        .call(contract.id(), "withdraw_referral_earnings_near")
+        .args_json(json!({}))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = referrer_account
+        .view(contract.id(), "get_referral_earnings_near")
+        .args_json(json!({
+            "account_id": referrer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(0)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_referral_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        contract,
+        producer_account,
+        consumer_account,
+        ..
+    } = crate::common::setup().await?;
+
+    for referrer in [consumer_account.id(), producer_account.id()] {
+        let outcome = consumer_account
+            .call(contract.id(), "request")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "request_data": "Hello World!",
+                "options": {
+                    "referrer": referrer,
+                },
+            }))
+            .transact()
+            .await?;
+        assert!(outcome.is_failure());
+    }
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 #![allow(clippy::bool_assert_comparison)]
+use near_sdk::NearToken;
 use serde_json::json;
 
 #[tokio::test]
@@ -18,6 +19,7 @@ async fn register_consumer() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
     assert!(!outcome.json::<bool>().unwrap());
 
+    // Storage has to be paid for
     let outcome = consumer_account
         .call(contract.id(), "register_consumer")
         .args_json(json!({
@@ -25,7 +27,51 @@ async fn register_consumer() -> Result<(), Box<dyn std::error::Error>> {
         }))
         .transact()
         .await?;
+    assert!(outcome.is_failure());
+
+    // Another account can pay for the registration
+    let sponsor_account = sandbox.dev_create_account().await?;
+    let sponsor_balance_before = sponsor_account.view_account().await?.balance;
+    let other_account = sandbox.dev_create_account().await?;
+    let outcome = sponsor_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": other_account.id(),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
     assert!(outcome.is_success());
+    let outcome = sponsor_account
+        .view(contract.id(), "is_registered_as_consumer")
+        .args_json(json!({
+            "account_id": other_account.id(),
+        }))
+        .await?;
+    assert!(outcome.json::<bool>().unwrap());
+    // and the rest of its deposit is refunded to it
+    let sponsor_balance_after = sponsor_account.view_account().await?.balance;
+    assert!(
+        sponsor_balance_before
+            .checked_sub(sponsor_balance_after)
+            .unwrap()
+            < NearToken::from_millinear(20)
+    );
+
+    let balance_before = consumer_account.view_account().await?.balance;
+    let outcome = consumer_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The rest of the deposit is refunded
+    let balance_after = consumer_account.view_account().await?.balance;
+    assert!(balance_before.checked_sub(balance_after).unwrap() < NearToken::from_millinear(20));
 
     let outcome = consumer_account
         .view(contract.id(), "is_registered_as_consumer")
@@ -72,3 +118,88 @@ async fn register_producer() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn unregister_consumer() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract_wasm = crate::get_contract_wasm().await;
+
+    let contract = sandbox.dev_deploy(contract_wasm).await?;
+
+    let consumer_account = sandbox.dev_create_account().await?;
+    let producer_account = sandbox.dev_create_account().await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "add_producer")
+        .args_json(json!({}))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .call(contract.id(), "register_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = consumer_account
+        .call(contract.id(), "deposit_near")
+        .args_json(json!({
+            "allocations": [
+                [producer_account.id(), NearToken::from_millinear(500)],
+            ],
+        }))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let balance_before = consumer_account.view_account().await?.balance;
+
+    let outcome = consumer_account
+        .call(contract.id(), "unregister_consumer")
+        .args_json(json!({
+            "force": false,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome.logs()[0].starts_with(&format!(
+        "Unregistered {account_id}, refunding 1.00",
+        account_id = consumer_account.id()
+    )));
+
+    let balance_after = consumer_account.view_account().await?.balance;
+    assert!(
+        balance_after
+            .checked_add(NearToken::from_millinear(5)) // gas fee
+            .unwrap()
+            .checked_sub(balance_before)
+            .unwrap()
+            > NearToken::from_millinear(1000)
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "is_registered_as_consumer")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert!(!outcome.json::<bool>().unwrap());
+
+    Ok(())
+}
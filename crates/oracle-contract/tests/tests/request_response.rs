@@ -1,3 +1,4 @@
+use near_sdk::NearToken;
 use serde_json::json;
 
 #[tokio::test]
@@ -22,6 +23,7 @@ async fn contract_is_operational() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -53,6 +55,23 @@ async fn contract_is_operational() -> Result<(), Box<dyn std::error::Error>> {
     let request_result = request.await?;
     assert!(request_result.is_success());
 
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    let latency = details["latency"]["average_latency_blocks"]
+        .as_u64()
+        .unwrap();
+    assert!(latency >= 1);
+    assert_eq!(details["latency"]["p95_latency_blocks"], latency);
+    let last_response_timestamp = details["latency"]["last_response_timestamp_nanos"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
     let logs = request_result.logs();
     assert_eq!(logs, vec![
         format!("EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"request\",\"data\":{{\"producer_id\":\"{producer}\",\"consumer_id\":\"{consumer}\",\"request_id\":\"0\",\"request_data\":\"Hello World!\"}}}}", 
@@ -63,7 +82,7 @@ async fn contract_is_operational() -> Result<(), Box<dyn std::error::Error>> {
             producer = producer_account.id()
         ),
         format!(
-            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_updated\",\"data\":{{\"account_id\":\"{producer}\",\"requests_succeded\":1,\"requests_timed_out\":0,\"fee\":\"None\",\"send_callback\":false,\"name\":\"Unnamed\",\"description\":\"No description\",\"example_input\":null}}}}",
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_updated\",\"data\":{{\"account_id\":\"{producer}\",\"requests_succeded\":1,\"requests_timed_out\":0,\"fee\":\"None\",\"send_callback\":false,\"name\":\"Unnamed\",\"description\":\"No description\",\"example_input\":null,\"sponsorship_rules\":{{\"free_requests_per_epoch\":0,\"unlimited_consumers\":[]}},\"volume_tiers\":[],\"beneficiaries\":[],\"referral_fee_bps\":0,\"latency\":{{\"average_latency_blocks\":{latency},\"p95_latency_blocks\":{latency},\"last_response_timestamp_nanos\":\"{last_response_timestamp}\"}},\"max_pending_requests\":null,\"rate_limit\":null,\"operators\":[],\"last_heartbeat_block\":null,\"liveness_threshold_blocks\":null,\"max_extensions\":0,\"encryption_public_key\":null,\"requests_rejected\":0,\"accepts_validators\":false}}}}",
             producer = producer_account.id()
         ),
     ]);
@@ -93,6 +112,7 @@ async fn request_timeout() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
@@ -122,7 +142,7 @@ async fn request_timeout() -> Result<(), Box<dyn std::error::Error>> {
             producer = producer_account.id()
         ),
         format!(
-            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_updated\",\"data\":{{\"account_id\":\"{producer}\",\"requests_succeded\":0,\"requests_timed_out\":1,\"fee\":\"None\",\"send_callback\":false,\"name\":\"Unnamed\",\"description\":\"No description\",\"example_input\":null}}}}",
+            "EVENT_JSON:{{\"standard\":\"intear-oracle\",\"version\":\"1.0.0\",\"event\":\"producer_updated\",\"data\":{{\"account_id\":\"{producer}\",\"requests_succeded\":0,\"requests_timed_out\":1,\"fee\":\"None\",\"send_callback\":false,\"name\":\"Unnamed\",\"description\":\"No description\",\"example_input\":null,\"sponsorship_rules\":{{\"free_requests_per_epoch\":0,\"unlimited_consumers\":[]}},\"volume_tiers\":[],\"beneficiaries\":[],\"referral_fee_bps\":0,\"latency\":{{\"average_latency_blocks\":null,\"p95_latency_blocks\":null,\"last_response_timestamp_nanos\":null}},\"max_pending_requests\":null,\"rate_limit\":null,\"operators\":[],\"last_heartbeat_block\":null,\"liveness_threshold_blocks\":null,\"max_extensions\":0,\"encryption_public_key\":null,\"requests_rejected\":0,\"accepts_validators\":false}}}}",
             producer = producer_account.id()
         ),
     ]);
@@ -0,0 +1,237 @@
+use intear_oracle::sponsorship::SponsorshipRules;
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn sponsored_requests() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "deposit_sponsorship_near")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(
+        outcome.logs(),
+        vec![format!(
+            "Deposited 1.00 NEAR to sponsorship pool of {producer_id}",
+            producer_id = producer_account.id()
+        )]
+    );
+
+    let outcome = producer_account
+        .call(contract.id(), "set_sponsorship_rules")
+        .args_json(json!({
+            "rules": SponsorshipRules {
+                free_requests_per_epoch: 1,
+                unlimited_consumers: vec![consumer_account.id().clone(); 51],
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_sponsorship_rules")
+        .args_json(json!({
+            "rules": SponsorshipRules {
+                free_requests_per_epoch: 1,
+                unlimited_consumers: vec![],
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsored_requests_left")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "consumer_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<u32>>().unwrap(), Some(1));
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsorship_balance_near")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100)
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsored_requests_left")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "consumer_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<u32>>().unwrap(), Some(0));
+
+    // Free quota is used up, and the consumer has no balance
+    let outcome = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+                "refund_amount": NearToken::from_millinear(40).as_yoctonear().to_string(),
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsorship_balance_near")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100 + 40)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sponsored_request_times_out() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "deposit_sponsorship_near")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_sponsorship_rules")
+        .args_json(json!({
+            "rules": SponsorshipRules {
+                free_requests_per_epoch: 1,
+                unlimited_consumers: vec![],
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "This will timeout",
+        }))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(250).await?;
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(request_result.json::<Option<serde_json::Value>>()?, None);
+
+    // Both the fee and the free request are given back
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsorship_balance_near")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(1)
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsored_requests_left")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "consumer_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<u32>>().unwrap(), Some(1));
+
+    let outcome = producer_account
+        .call(contract.id(), "withdraw_sponsorship_near")
+        .args_json(json!({
+            "amount": NearToken::from_near(1),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "withdraw_sponsorship_near")
+        .args_json(json!({
+            "amount": NearToken::from_near(1),
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_sponsorship_balance_near")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(0)
+    );
+
+    Ok(())
+}
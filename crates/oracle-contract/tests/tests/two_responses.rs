@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use near_sdk::NearToken;
 use serde_json::json;
 
 #[tokio::test]
@@ -24,6 +25,7 @@ async fn cannot_send_two_responses() -> Result<(), Box<dyn std::error::Error>> {
         .args_json(json!({
             "account_id": consumer_account.id(),
         }))
+        .deposit(NearToken::from_millinear(100))
         .transact()
         .await?;
     assert!(outcome.is_success());
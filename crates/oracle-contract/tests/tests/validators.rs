@@ -0,0 +1,237 @@
+use near_sdk::NearToken;
+use near_workspaces::{Account, Contract};
+use serde_json::json;
+
+async fn accept_validators(
+    contract: &Contract,
+    producer_account: &Account,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = producer_account
+        .call(contract.id(), "set_accepts_validators")
+        .args_json(json!({
+            "accepts_validators": true,
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    Ok(())
+}
+
+#[tokio::test]
+async fn validator_needs_opt_in() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let validator_account = sandbox.dev_create_account().await?;
+
+    let request_with_validator = |validator_id: &near_workspaces::AccountId| {
+        consumer_account
+            .call(contract.id(), "request")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "request_data": "Hello World!",
+                "options": {
+                    "validator": {
+                        "contract_id": validator_id,
+                        "method_name": "validate",
+                        "gas": "5000000000000",
+                    },
+                },
+            }))
+            .max_gas()
+    };
+
+    let outcome = request_with_validator(validator_account.id())
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Producer doesn't accept validators"));
+
+    accept_validators(&contract, &producer_account).await?;
+
+    // The validator would be called by the oracle itself, so it could
+    // call private methods
+    let outcome = request_with_validator(contract.id()).transact().await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.into_result().unwrap_err())
+        .contains("Validator can't be the oracle contract"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failing_validator_accepts_response() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let validator_account = sandbox.dev_create_account().await?;
+    accept_validators(&contract, &producer_account).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+            "options": {
+                "validator": {
+                    "contract_id": validator_account.id(),
+                    "method_name": "validate",
+                    "gas": "5000000000000",
+                },
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    // The validator account has no contract, so the call fails, which
+    // doesn't count against the producer
+    assert!(request_result
+        .receipt_outcomes()
+        .iter()
+        .any(|outcome| &outcome.executor_id == validator_account.id()));
+    let response = request_result.json::<Option<serde_json::Value>>()?.unwrap();
+    assert_eq!(response["response_data"], "Hello Yielded Execution!");
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_producer_details")
+        .args_json(json!({
+            "account_id": producer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["requests_succeded"], 1);
+    assert_eq!(details["requests_rejected"], 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejected_response_is_refunded() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+    let validator_contract = sandbox
+        .dev_deploy(crate::get_validator_contract_wasm().await)
+        .await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+    accept_validators(&contract, &producer_account).await?;
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Give me JSON",
+            "options": {
+                "validator": {
+                    "contract_id": validator_contract.id(),
+                    "method_name": "validate",
+                    "gas": "5000000000000",
+                },
+            },
+        }))
+        .max_gas()
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    // The fee is locked until the response is validated
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(900)
+    );
+
+    let producer_balance_before = producer_account.view_account().await?.balance;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "0",
+            "response": {
+                "response_data": "Not JSON",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+    assert_eq!(request_result.json::<Option<serde_json::Value>>()?, None);
+    let logs = request_result.logs();
+    assert!(logs.contains(&"Response for 0 was rejected by the validator"));
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("\"event\":\"producer_updated\"")
+            && log.contains("\"requests_succeded\":0,\"requests_timed_out\":0,")
+            && log.contains("\"requests_rejected\":1,\"accepts_validators\":true}")));
+
+    sandbox.fast_forward(2).await?;
+
+    // The producer only paid for gas
+    let producer_balance_after = producer_account.view_account().await?.balance;
+    assert!(producer_balance_after < producer_balance_before);
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_near(1)
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_consumer_details")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    let details = outcome.json::<serde_json::Value>().unwrap();
+    assert_eq!(details["requests_succeeded"], 0);
+    assert_eq!(details["requests_timed_out"], 0);
+    assert_eq!(details["requests_rejected"], 1);
+    assert_eq!(details["producers"][0]["requests_rejected"], 1);
+
+    Ok(())
+}
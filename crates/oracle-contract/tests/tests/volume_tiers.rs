@@ -0,0 +1,173 @@
+use intear_oracle::fees::{ProducerFee, VolumeTier};
+use near_sdk::NearToken;
+use serde_json::json;
+
+#[tokio::test]
+async fn volume_discount() -> Result<(), Box<dyn std::error::Error>> {
+    let crate::common::Env {
+        sandbox,
+        contract,
+        producer_account,
+        consumer_account,
+    } = crate::common::setup().await?;
+
+    crate::common::set_near_fee(&contract, &producer_account, NearToken::from_millinear(100))
+        .await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "set_volume_tiers")
+        .args_json(json!({
+            "volume_tiers": (1..=11)
+                .map(|min_requests| VolumeTier {
+                    min_requests,
+                    discount_bps: 100,
+                })
+                .collect::<Vec<_>>(),
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    let outcome = producer_account
+        .call(contract.id(), "set_volume_tiers")
+        .args_json(json!({
+            "volume_tiers": [
+                VolumeTier {
+                    min_requests: 1,
+                    discount_bps: 5000, // 50%
+                },
+            ],
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    crate::common::deposit_near(&contract, &consumer_account, NearToken::from_near(1)).await?;
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_volume_tier")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "consumer_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(outcome.json::<Option<VolumeTier>>().unwrap(), None);
+
+    for request_id in 0..2 {
+        let request = consumer_account
+            .call(contract.id(), "request")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "request_data": "Hello World!",
+            }))
+            .transact_async()
+            .await?;
+
+        sandbox.fast_forward(1).await?;
+
+        let outcome = producer_account
+            .call(contract.id(), "respond")
+            .args_json(json!({
+                "request_id": request_id.to_string(),
+                "response": {
+                    "response_data": "Hello Yielded Execution!",
+                }
+            }))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+
+        let request_result = request.await?;
+        assert!(request_result.is_success());
+
+        let outcome = consumer_account
+            .view(contract.id(), "get_volume_tier")
+            .args_json(json!({
+                "producer_id": producer_account.id(),
+                "consumer_id": consumer_account.id(),
+            }))
+            .await?;
+        assert_eq!(
+            outcome.json::<Option<VolumeTier>>().unwrap(),
+            Some(VolumeTier {
+                min_requests: 1,
+                discount_bps: 5000,
+            })
+        );
+    }
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_fee_for_consumer")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "consumer_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<Option<ProducerFee>>().unwrap(),
+        Some(ProducerFee::Near {
+            prepaid_amount: NearToken::from_millinear(50),
+        })
+    );
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100 - 50)
+    );
+
+    // The discount also applies to the fee attached to the call, and
+    // the rest of the deposit is refunded
+    let balance_before = consumer_account.view_account().await?.balance;
+    let request = consumer_account
+        .call(contract.id(), "request")
+        .args_json(json!({
+            "producer_id": producer_account.id(),
+            "request_data": "Hello World!",
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .transact_async()
+        .await?;
+
+    sandbox.fast_forward(1).await?;
+
+    let outcome = producer_account
+        .call(contract.id(), "respond")
+        .args_json(json!({
+            "request_id": "2",
+            "response": {
+                "response_data": "Hello Yielded Execution!",
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let request_result = request.await?;
+    assert!(request_result.is_success());
+
+    let balance_after = consumer_account.view_account().await?.balance;
+    let spent = balance_before.checked_sub(balance_after).unwrap();
+    assert!(spent >= NearToken::from_millinear(50));
+    assert!(spent < NearToken::from_millinear(75));
+
+    let outcome = consumer_account
+        .view(contract.id(), "get_deposit_near")
+        .args_json(json!({
+            "account_id": consumer_account.id(),
+        }))
+        .await?;
+    assert_eq!(
+        outcome.json::<NearToken>().unwrap(),
+        NearToken::from_millinear(1000 - 100 - 50)
+    );
+
+    Ok(())
+}
@@ -7,8 +7,11 @@ use intear_oracle::fees::PrepaidFee;
 use intear_oracle::producer::{ext_oracle_responder, ProducerContract, Response};
 use near_sdk::Gas;
 use near_sdk::{
-    env, json_types::U128, near, require, store::LookupMap, AccountId, NearToken, PanicOnDefault,
-    Promise, PromiseError,
+    env,
+    json_types::{Base58CryptoHash, Base64VecU8, U128},
+    near, require,
+    store::LookupMap,
+    AccountId, NearToken, PanicOnDefault, Promise, PromiseError,
 };
 use reclaim::{ext_reclaim, Proof};
 
@@ -118,7 +121,9 @@ impl Contract {
 
         let response = Response {
             response_data: near_sdk::serde_json::to_string(&result).unwrap(),
+            response_bytes: None,
             refund_amount: Some(U128(refund_amount)),
+            response_blob: None,
         };
         Promise::new(sender_account_id).transfer(NearToken::from_yoctonear(price_near));
         ext_oracle_responder::ext(self.oracle_contract.clone()).respond(request_id, response)
@@ -127,7 +132,15 @@ impl Contract {
 
 #[near]
 impl ProducerContract for Contract {
-    fn on_request(&mut self, request_id: RequestId, request_data: String, prepaid_fee: PrepaidFee) {
+    fn on_request(
+        &mut self,
+        request_id: RequestId,
+        request_data: String,
+        prepaid_fee: PrepaidFee,
+        _end_user: Option<String>,
+        _request_bytes: Option<Base64VecU8>,
+        _request_blob: Option<Base58CryptoHash>,
+    ) {
         if env::predecessor_account_id() != self.oracle_contract {
             env::panic_str("Only oracle contract can call this method");
         }
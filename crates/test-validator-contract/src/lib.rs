@@ -0,0 +1,27 @@
+use intear_oracle::{consumer::RequestId, producer::Response};
+use near_sdk::{log, near, serde_json};
+
+#[derive(Default)]
+#[near(contract_state)]
+pub struct ValidatorContract {}
+
+#[near]
+impl ValidatorContract {
+    /// Called by the oracle with `request_id`, `producer_id` and
+    /// `response`, only the response is checked.
+    pub fn validate(&self, response: Response) -> bool {
+        serde_json::from_str::<serde_json::Value>(&response.response_data).is_ok()
+    }
+
+    /// Response callback that logs the arguments it was called with,
+    /// so that tests can check what was delivered.
+    pub fn on_oracle_response(&self, request_id: RequestId, response: Option<Response>) {
+        log!(
+            "{}",
+            serde_json::json!({
+                "request_id": request_id,
+                "response": response,
+            })
+        );
+    }
+}